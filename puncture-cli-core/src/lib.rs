@@ -1,11 +1,26 @@
 use bitcoin::address::NetworkUnchecked;
 use bitcoin::secp256k1::PublicKey;
 use bitcoin::{Address, OutPoint};
-use clap::Args;
+use std::fmt;
+use std::str::FromStr;
+
+use clap::{Args, ValueEnum};
 use serde::{Deserialize, Serialize};
 
+/// The file in the daemon's data directory containing the admin token
+pub const ADMIN_TOKEN_FILE: &str = "admin.token";
+
+/// The file in the daemon's data directory containing the read-only token
+pub const READ_ONLY_TOKEN_FILE: &str = "read_only.token";
+
+/// The ALPN of the admin interface served over the daemon's iroh endpoint
+pub const ADMIN_ALPN: &[u8] = b"puncture-admin";
+
+pub const ROUTE_STATUS: &str = "/status";
 pub const ROUTE_LDK_NODE_ID: &str = "/ldk/node-id";
 pub const ROUTE_LDK_BALANCES: &str = "/ldk/balances";
+pub const ROUTE_LDK_SOLVENCY: &str = "/ldk/solvency";
+pub const ROUTE_LDK_SOLVENCY_PUBLISH: &str = "/ldk/solvency/publish";
 pub const ROUTE_LDK_ONCHAIN_RECEIVE: &str = "/ldk/onchain/receive";
 pub const ROUTE_LDK_ONCHAIN_SEND: &str = "/ldk/onchain/send";
 pub const ROUTE_LDK_ONCHAIN_DRAIN: &str = "/ldk/onchain/drain";
@@ -13,12 +28,56 @@ pub const ROUTE_LDK_CHANNEL_OPEN: &str = "/ldk/channel/open";
 pub const ROUTE_LDK_CHANNEL_CLOSE: &str = "/ldk/channel/close";
 pub const ROUTE_LDK_CHANNEL_LIST: &str = "/ldk/channel/list";
 pub const ROUTE_LDK_CHANNEL_REQUEST: &str = "/ldk/channel/request";
+pub const ROUTE_LDK_CHANNEL_CONFIG: &str = "/ldk/channel/config";
+pub const ROUTE_LDK_FORWARDING_REPORT: &str = "/ldk/forwarding/report";
+pub const ROUTE_LDK_CHANNEL_ORDER_CREATE: &str = "/ldk/channel/order/create";
+pub const ROUTE_LDK_CHANNEL_ORDER_PAY: &str = "/ldk/channel/order/pay";
+pub const ROUTE_LDK_CHANNEL_ORDER_REFRESH: &str = "/ldk/channel/order/refresh";
+pub const ROUTE_LDK_CHANNEL_ORDER_LIST: &str = "/ldk/channel/order/list";
 pub const ROUTE_LDK_PEER_CONNECT: &str = "/ldk/peer/connect";
 pub const ROUTE_LDK_PEER_DISCONNECT: &str = "/ldk/peer/disconnect";
 pub const ROUTE_LDK_PEER_LIST: &str = "/ldk/peer/list";
 pub const ROUTE_USER_INVITE: &str = "/user/invite";
+pub const ROUTE_USER_INVITE_LIST: &str = "/user/invite/list";
+pub const ROUTE_USER_INVITE_SHOW: &str = "/user/invite/show";
+pub const ROUTE_USER_INVITE_REVOKE: &str = "/user/invite/revoke";
 pub const ROUTE_USER_RECOVER: &str = "/user/recover";
 pub const ROUTE_USER_LIST: &str = "/user/list";
+pub const ROUTE_USER_STATUS: &str = "/user/status";
+pub const ROUTE_USER_ADJUST: &str = "/user/adjust";
+pub const ROUTE_SECRET_EXPORT: &str = "/secret/export";
+pub const ROUTE_SECRET_ROTATE: &str = "/secret/rotate";
+pub const ROUTE_AUDIT_LIST: &str = "/audit/list";
+pub const ROUTE_ADMIN_EXPORT: &str = "/admin/export";
+pub const ROUTE_ADMIN_IMPORT: &str = "/admin/import";
+pub const ROUTE_BACKUP_CREATE: &str = "/backup/create";
+pub const ROUTE_BACKUP_LIST: &str = "/backup/list";
+pub const ROUTE_CONFIG_RELOAD: &str = "/config/reload";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusResponse {
+    /// Whether the node is running and the database is reachable
+    pub healthy: bool,
+    /// Whether the daemon is healthy, synced to the chain, has a usable
+    /// channel and is reachable by clients via its home relay
+    pub ready: bool,
+    /// Whether the LDK node is running
+    pub node_running: bool,
+    /// The height of the best block the node is synced to
+    pub block_height: u32,
+    /// The hash of the best block the node is synced to
+    pub block_hash: String,
+    /// The time of the last successful sync of the on-chain wallet
+    pub latest_onchain_wallet_sync: Option<u64>,
+    /// The time of the last successful sync of the lightning wallet
+    pub latest_lightning_wallet_sync: Option<u64>,
+    /// The number of channels that can currently be used to send or receive
+    pub usable_channels: usize,
+    /// Whether the database responded to a query
+    pub database_reachable: bool,
+    /// The relay our iroh endpoint is connected to and reachable via
+    pub home_relay: Option<String>,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NodeIdResponse {
@@ -36,6 +95,34 @@ pub struct BalancesResponse {
     pub total_outbound_capacity_msat: u64,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SolvencyResponse {
+    /// The balance of the on-chain wallet in millisatoshis
+    pub onchain_msat: u64,
+    /// The outbound capacity across all channels in millisatoshis
+    pub lightning_msat: u64,
+    /// The sum of the on-chain balance and the outbound capacity
+    pub assets_msat: u64,
+    /// The sum of all user balances
+    pub liabilities_msat: u64,
+    /// The assets minus the liabilities, negative if we are insolvent
+    pub surplus_msat: i64,
+    /// The number of users included in the liabilities
+    pub user_count: u64,
+    /// The last published Merkle sum tree of all user balances
+    pub published: Option<PublishedLiabilities>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishedLiabilities {
+    /// The root hash users verify the inclusion of their balance against
+    pub root_hash: String,
+    /// The total liabilities committed to by the root
+    pub liabilities_msat: u64,
+    /// The time the tree was published
+    pub created_at: i64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OnchainReceiveResponse {
     /// The generated Bitcoin address
@@ -76,6 +163,9 @@ pub struct OpenChannelRequest {
     /// Whether to announce the channel publicly
     #[arg(long)]
     pub public: bool,
+    /// The name of the LSP to request the channel from, defaults to the first configured one
+    #[arg(long)]
+    pub provider: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -119,6 +209,76 @@ pub struct ChannelInfo {
     pub confirmations: Option<u32>,
     /// Number of confirmations required to be usable
     pub confirmations_required: Option<u32>,
+    /// The forwarding policy of the channel
+    pub config: ChannelConfigInfo,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChannelConfigInfo {
+    /// Base fee in millisatoshis charged for forwarding over the channel
+    pub forwarding_fee_base_msat: u32,
+    /// Fee in millionths of the amount charged for forwarding over the channel
+    pub forwarding_fee_proportional_millionths: u32,
+    /// Difference between the CLTV expiry of incoming HTLCs and HTLCs forwarded over the channel
+    pub cltv_expiry_delta: u16,
+    /// Fixed limit in millisatoshis of our exposure to dust HTLCs, if set
+    pub max_dust_htlc_exposure_msat: Option<u64>,
+    /// Multiplier on the fee rate limiting our exposure to dust HTLCs, if set
+    pub max_dust_htlc_exposure_multiplier: Option<u64>,
+}
+
+#[derive(Debug, Clone, Args, Serialize, Deserialize)]
+pub struct UpdateChannelConfigRequest {
+    /// User channel ID in hex encoding
+    pub user_channel_id: String,
+    /// Counterparty node public key
+    pub counterparty_node_id: PublicKey,
+    /// Base fee in millisatoshis charged for forwarding over the channel
+    #[arg(long)]
+    pub forwarding_fee_base_msat: Option<u32>,
+    /// Fee in millionths of the amount charged for forwarding over the channel
+    #[arg(long)]
+    pub forwarding_fee_proportional_millionths: Option<u32>,
+    /// Difference between the CLTV expiry of incoming HTLCs and HTLCs forwarded over the channel
+    #[arg(long)]
+    pub cltv_expiry_delta: Option<u16>,
+    /// Fixed limit in millisatoshis of our exposure to dust HTLCs
+    #[arg(long, conflicts_with = "max_dust_htlc_exposure_multiplier")]
+    pub max_dust_htlc_exposure_msat: Option<u64>,
+    /// Multiplier on the fee rate limiting our exposure to dust HTLCs
+    #[arg(long)]
+    pub max_dust_htlc_exposure_multiplier: Option<u64>,
+}
+
+#[derive(Debug, Clone, Args, Serialize, Deserialize)]
+pub struct ForwardingReportRequest {
+    /// Only include forwards of the last number of days
+    #[arg(long)]
+    pub since_days: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelForwardingSummary {
+    /// Channel ID of the outgoing channel in hex encoding
+    pub channel_id: String,
+    /// Number of payments forwarded over the channel
+    pub forwards: u64,
+    /// Fees earned in millisatoshis
+    pub fee_earned_msat: u64,
+    /// Amount forwarded in millisatoshis
+    pub amount_forwarded_msat: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForwardingReportResponse {
+    /// Number of payments forwarded
+    pub forwards: u64,
+    /// Fees earned in millisatoshis
+    pub fee_earned_msat: u64,
+    /// Amount forwarded in millisatoshis
+    pub amount_forwarded_msat: u64,
+    /// The forwards grouped by outgoing channel, highest earning first
+    pub channels: Vec<ChannelForwardingSummary>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -140,14 +300,98 @@ pub struct RequestChannelRequest {
     /// Whether to announce the channel publicly
     #[arg(long)]
     pub public: bool,
+    /// The name of the LSP to request the channel from, defaults to the first configured one
+    #[arg(long)]
+    pub provider: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RequestChannelResponse {
+    /// The name of the LSP the channel was requested from
+    pub provider: String,
     /// The BOLT11 invoice
     pub invoice: String,
 }
 
+#[derive(Debug, Clone, Args, Serialize, Deserialize)]
+pub struct CreateChannelOrderRequest {
+    /// The balance of the LSP in satoshis
+    pub lsp_balance_sat: u64,
+    /// The balance of the client in satoshis
+    #[arg(long, default_value = "0")]
+    pub client_balance_sat: u64,
+    /// The number of blocks until the channel expires
+    #[arg(long, default_value = "13140")]
+    pub channel_expiry_blocks: u32,
+    /// Whether to announce the channel publicly
+    #[arg(long)]
+    pub public: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderPaymentMethod {
+    /// Pay the order's invoice from our channels
+    Lightning,
+    /// Pay the order's address from our on-chain wallet
+    Onchain,
+}
+
+#[derive(Debug, Clone, Args, Serialize, Deserialize)]
+pub struct PayChannelOrderRequest {
+    /// The id of the order assigned by the LSP
+    pub order_id: String,
+    /// Whether to pay from our channels or our on-chain wallet
+    #[arg(long, value_enum, default_value = "lightning")]
+    pub method: OrderPaymentMethod,
+}
+
+#[derive(Debug, Clone, Args, Serialize, Deserialize)]
+pub struct ChannelOrderIdRequest {
+    /// The id of the order assigned by the LSP
+    pub order_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelOrderInfo {
+    /// The id of the order assigned by the LSP
+    pub order_id: String,
+    /// The node id of the LSP
+    pub lsp_node_id: String,
+    /// The balance of the LSP in satoshis
+    pub lsp_balance_sat: u64,
+    /// The balance of the client in satoshis
+    pub client_balance_sat: u64,
+    /// The number of blocks until the channel expires
+    pub channel_expiry_blocks: u32,
+    /// Whether the channel will be announced publicly
+    pub announce_channel: bool,
+    /// The fee charged by the LSP in satoshis
+    pub fee_total_sat: u64,
+    /// The fee plus the client balance we have to pay in satoshis
+    pub order_total_sat: u64,
+    /// The invoice to pay the order via lightning, if offered
+    pub invoice: Option<String>,
+    /// The address to pay the order on-chain, if offered
+    pub onchain_address: Option<String>,
+    /// The payment state reported by the LSP: expect_payment, paid or refunded
+    pub payment_state: String,
+    /// Our payment id or transaction id once we paid the order
+    pub payment: Option<String>,
+    /// The funding outpoint once the LSP opened the channel
+    pub funding_outpoint: Option<String>,
+    /// The time the order was created
+    pub created_at: i64,
+    /// The time the order was last updated from the LSP
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListChannelOrdersResponse {
+    /// All channel orders, newest first
+    pub orders: Vec<ChannelOrderInfo>,
+}
+
 #[derive(Debug, Clone, Args, Serialize, Deserialize)]
 pub struct ConnectPeerRequest {
     /// The public key of the peer to connect to
@@ -199,6 +443,44 @@ pub struct InviteResponse {
     pub invite: String,
 }
 
+#[derive(Debug, Clone, Args, Serialize, Deserialize)]
+pub struct InviteIdRequest {
+    /// The invite id or the invite code itself
+    pub invite_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InviteInfo {
+    /// The invite id
+    pub id: String,
+    /// Maximum number of users that can register with this invite
+    pub user_limit: u32,
+    /// Number of users that registered with this invite
+    pub user_count: u32,
+    /// Timestamp in milliseconds since the Unix epoch
+    pub expires_at: i64,
+    /// Timestamp in milliseconds since the Unix epoch
+    pub created_at: i64,
+    /// Timestamp in milliseconds since the Unix epoch if the invite was revoked
+    pub revoked_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListInvitesResponse {
+    /// List of invites, newest first
+    pub invites: Vec<InviteInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShowInviteResponse {
+    /// The invite's details
+    pub info: InviteInfo,
+    /// The invite in hex encoding
+    pub invite: String,
+    /// The public keys of the users that registered with this invite
+    pub users: Vec<String>,
+}
+
 #[derive(Debug, Clone, Args, Serialize, Deserialize)]
 pub struct RecoverRequest {
     /// The user's public key
@@ -224,6 +506,85 @@ pub struct UserInfo {
     pub recovery_name: Option<String>,
     /// Timestamp in milliseconds since the Unix epoch
     pub created_at: i64,
+    /// Whether the user may send, receive or use the daemon at all
+    pub status: UserStatus,
+    /// The operator's reason for the current status
+    pub status_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum UserStatus {
+    /// The user can send and receive
+    Active,
+    /// The user can still receive but not send or be recovered from
+    Frozen,
+    /// The user cannot use the daemon until reactivated
+    Suspended,
+    /// The user cannot use the daemon and cannot be reactivated
+    Deleted,
+}
+
+impl UserStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserStatus::Active => "active",
+            UserStatus::Frozen => "frozen",
+            UserStatus::Suspended => "suspended",
+            UserStatus::Deleted => "deleted",
+        }
+    }
+}
+
+impl fmt::Display for UserStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for UserStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "active" => Ok(UserStatus::Active),
+            "frozen" => Ok(UserStatus::Frozen),
+            "suspended" => Ok(UserStatus::Suspended),
+            "deleted" => Ok(UserStatus::Deleted),
+            _ => Err(format!("Unknown user status {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Args, Serialize, Deserialize)]
+pub struct SetUserStatusRequest {
+    /// The user's public key
+    pub user_pk: String,
+    /// The new status of the user
+    pub status: UserStatus,
+    /// The reason for the status change, shown to the user
+    #[arg(long)]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Args, Serialize, Deserialize)]
+pub struct AdjustBalanceRequest {
+    /// The user's public key
+    pub user_pk: String,
+    /// The amount to credit in millisatoshis, negative to debit the user
+    #[arg(long, allow_negative_numbers = true)]
+    pub amount_msat: i64,
+    /// The reason for the adjustment, shown in the user's payment history
+    #[arg(long)]
+    pub memo: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdjustBalanceResponse {
+    /// The id of the adjustment in the user's payment history
+    pub id: String,
+    /// The user's balance after the adjustment in millisatoshis
+    pub balance_msat: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -231,3 +592,134 @@ pub struct ListUsersResponse {
     /// List of user information
     pub users: Vec<UserInfo>,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecretExportResponse {
+    /// The daemon's secret key as a BIP39 mnemonic
+    pub mnemonic: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecretRotateResponse {
+    /// The node id of the retired secret key
+    pub old_node_id: String,
+    /// The node id of the new secret key, used after the daemon restarts
+    pub new_node_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupInfo {
+    /// The directory containing the backup on the daemon's host
+    pub path: String,
+    /// The creation time of the backup
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListBackupsResponse {
+    /// The retained backups, oldest first
+    pub backups: Vec<BackupInfo>,
+}
+
+/// An archive of all user accounts signed by the exporting daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedArchive {
+    /// The node id of the daemon that exported the archive
+    pub node_id: String,
+    /// The JSON encoded archive
+    pub archive: String,
+    /// The hex encoded signature by the node id over the archive
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportResponse {
+    /// The number of imported users
+    pub user_count: u64,
+    /// The number of imported invites
+    pub invite_count: u64,
+    /// The number of imported payments and adjustments
+    pub payment_count: u64,
+    /// The sum of all imported user balances
+    pub liabilities_msat: u64,
+}
+
+#[derive(Debug, Clone, Args, Serialize, Deserialize)]
+pub struct AuditListRequest {
+    /// Only list entries by actors starting with this prefix, e.g. cli, ui or node
+    #[arg(long)]
+    pub actor: Option<String>,
+    /// Only list entries of this action, e.g. /ldk/onchain/drain
+    #[arg(long)]
+    pub action: Option<String>,
+    /// Only list entries of the last number of days
+    #[arg(long)]
+    pub since_days: Option<u32>,
+    /// Only list entries that failed
+    #[arg(long)]
+    pub failed: bool,
+    /// Maximum number of entries to list, newest first
+    #[arg(long, default_value = "100")]
+    pub limit: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// The entry id
+    pub id: String,
+    /// Who performed the action: cli:admin, node:<node-id> or ui:<session>
+    pub actor: String,
+    /// The CLI route or UI form that was submitted
+    pub action: String,
+    /// The submitted request body
+    pub request: String,
+    /// The HTTP status code of the response
+    pub status: u16,
+    /// The response body of CLI requests, redacted for secrets
+    pub result: Option<String>,
+    /// Timestamp in milliseconds since the Unix epoch
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditListResponse {
+    /// The matching audit log entries, newest first
+    pub entries: Vec<AuditEntry>,
+}
+
+/// A request to a CLI route sent over the admin ALPN
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminRpcRequest {
+    /// The CLI route to call
+    pub route: String,
+    /// The JSON request body
+    pub request: serde_json::Value,
+}
+
+/// The response of a CLI route called over the admin ALPN
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminRpcResponse {
+    /// The HTTP status code returned by the route
+    pub status: u16,
+    /// The response body, JSON on success and an error message otherwise
+    pub body: String,
+}
+
+/// The fee and limit settings that can be changed without restarting the daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Settings {
+    /// Fee rate in parts per million applied to outgoing Lightning payments
+    pub fee_ppm: u64,
+    /// Fixed base fee added to all outgoing Lightning payments
+    pub base_fee_msat: u64,
+    /// Fixed base fee added to all outgoing on-chain payments
+    pub onchain_base_fee_msat: u64,
+    /// Expiration time of generated Lightning invoices
+    pub invoice_expiry_secs: u32,
+    /// Minimum amount of incoming and outgoing payments
+    pub min_amount_sats: u32,
+    /// Maximum amount of incoming and outgoing payments
+    pub max_amount_sats: u32,
+    /// Maximum number of pending invoices and outgoing payments per user
+    pub max_pending_payments_per_user: u32,
+}
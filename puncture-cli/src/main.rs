@@ -1,27 +1,95 @@
+use std::path::PathBuf;
+
 use anyhow::{Context, Result, ensure};
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use iroh::{Endpoint, RelayMode};
 use serde::Serialize;
 use serde_json::Value;
 
 use puncture_cli_core::{
-    CloseChannelRequest, ConnectPeerRequest, DisconnectPeerRequest, InviteRequest,
-    OnchainDrainRequest, OnchainSendRequest, OpenChannelRequest, ROUTE_LDK_BALANCES,
-    ROUTE_LDK_CHANNEL_CLOSE, ROUTE_LDK_CHANNEL_LIST, ROUTE_LDK_CHANNEL_OPEN,
-    ROUTE_LDK_CHANNEL_REQUEST, ROUTE_LDK_NODE_ID, ROUTE_LDK_ONCHAIN_DRAIN,
+    ADMIN_ALPN, ADMIN_TOKEN_FILE, AdjustBalanceRequest, AdminRpcRequest, AdminRpcResponse,
+    AuditListRequest, ChannelOrderIdRequest, CloseChannelRequest, ConnectPeerRequest,
+    CreateChannelOrderRequest, DisconnectPeerRequest, ForwardingReportRequest, InviteIdRequest,
+    InviteRequest, OnchainDrainRequest, OnchainSendRequest, OpenChannelRequest,
+    PayChannelOrderRequest, ROUTE_ADMIN_EXPORT, ROUTE_ADMIN_IMPORT, ROUTE_AUDIT_LIST,
+    ROUTE_BACKUP_CREATE, ROUTE_BACKUP_LIST, ROUTE_CONFIG_RELOAD, ROUTE_LDK_BALANCES,
+    ROUTE_LDK_CHANNEL_CLOSE, ROUTE_LDK_CHANNEL_CONFIG, ROUTE_LDK_CHANNEL_LIST,
+    ROUTE_LDK_CHANNEL_OPEN, ROUTE_LDK_CHANNEL_ORDER_CREATE, ROUTE_LDK_CHANNEL_ORDER_LIST,
+    ROUTE_LDK_CHANNEL_ORDER_PAY, ROUTE_LDK_CHANNEL_ORDER_REFRESH, ROUTE_LDK_CHANNEL_REQUEST,
+    ROUTE_LDK_FORWARDING_REPORT, ROUTE_LDK_NODE_ID, ROUTE_LDK_ONCHAIN_DRAIN,
     ROUTE_LDK_ONCHAIN_RECEIVE, ROUTE_LDK_ONCHAIN_SEND, ROUTE_LDK_PEER_CONNECT,
-    ROUTE_LDK_PEER_DISCONNECT, ROUTE_LDK_PEER_LIST, ROUTE_USER_INVITE, ROUTE_USER_LIST,
-    ROUTE_USER_RECOVER, RecoverRequest, RequestChannelRequest,
+    ROUTE_LDK_PEER_DISCONNECT, ROUTE_LDK_PEER_LIST, ROUTE_LDK_SOLVENCY, ROUTE_LDK_SOLVENCY_PUBLISH,
+    ROUTE_SECRET_EXPORT, ROUTE_SECRET_ROTATE, ROUTE_STATUS, ROUTE_USER_ADJUST, ROUTE_USER_INVITE,
+    ROUTE_USER_INVITE_LIST, ROUTE_USER_INVITE_REVOKE, ROUTE_USER_INVITE_SHOW, ROUTE_USER_LIST,
+    ROUTE_USER_RECOVER, ROUTE_USER_STATUS, RecoverRequest, RequestChannelRequest,
+    SetUserStatusRequest, SignedArchive, UpdateChannelConfigRequest,
 };
+use puncture_core::secret;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
+    #[command(flatten)]
+    connection: ConnectionArgs,
+
+    #[command(subcommand)]
+    command: AdminCommands,
+}
+
+#[derive(Args, Debug)]
+struct ConnectionArgs {
     /// The port for the Puncture CLI to connect to.
     #[arg(long, env = "CLI_PORT", default_value = "8082")]
     cli_port: u16,
 
-    #[command(subcommand)]
-    command: AdminCommands,
+    /// The admin or read-only token to authenticate with.
+    #[arg(long, env = "PUNCTURE_ADMIN_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+
+    /// The file to read the token from, defaults to the admin token in PUNCTURE_DATA_DIR.
+    #[arg(long, env = "PUNCTURE_ADMIN_TOKEN_FILE", conflicts_with = "token")]
+    token_file: Option<PathBuf>,
+
+    /// Node id of a remote daemon to manage over iroh instead of the local CLI port.
+    #[arg(long, env = "PUNCTURE_DAEMON")]
+    daemon: Option<iroh::NodeId>,
+
+    /// Directory storing the identity presented to remote daemons, defaults to ~/.puncture-cli.
+    #[arg(long, env = "PUNCTURE_CLI_DATA_DIR")]
+    cli_data_dir: Option<PathBuf>,
+}
+
+impl ConnectionArgs {
+    fn token(&self) -> Result<String> {
+        if let Some(token) = &self.token {
+            return Ok(token.clone());
+        }
+
+        let token_file = match &self.token_file {
+            Some(token_file) => token_file.clone(),
+            None => PathBuf::from(std::env::var("PUNCTURE_DATA_DIR").context(
+                "No token given, set PUNCTURE_ADMIN_TOKEN, PUNCTURE_ADMIN_TOKEN_FILE or PUNCTURE_DATA_DIR",
+            )?)
+            .join(ADMIN_TOKEN_FILE),
+        };
+
+        Ok(std::fs::read_to_string(&token_file)
+            .context("Failed to read token file")?
+            .trim()
+            .to_string())
+    }
+
+    fn identity(&self) -> Result<iroh::SecretKey> {
+        let cli_data_dir = match &self.cli_data_dir {
+            Some(cli_data_dir) => cli_data_dir.clone(),
+            None => PathBuf::from(std::env::var("HOME").context("HOME is not set")?)
+                .join(".puncture-cli"),
+        };
+
+        std::fs::create_dir_all(&cli_data_dir).context("Failed to create cli data directory")?;
+
+        secret::read_or_generate(&cli_data_dir, None)
+    }
 }
 
 #[derive(Subcommand, Debug)]
@@ -36,6 +104,35 @@ enum AdminCommands {
         #[command(subcommand)]
         command: AdminUserCommands,
     },
+    /// Secret key backup commands
+    Secret {
+        #[command(subcommand)]
+        command: AdminSecretCommands,
+    },
+    /// Audit log commands
+    Audit {
+        #[command(subcommand)]
+        command: AdminAuditCommands,
+    },
+    /// Database backup commands
+    Backup {
+        #[command(subcommand)]
+        command: AdminBackupCommands,
+    },
+    /// Fee and limit settings commands
+    Config {
+        #[command(subcommand)]
+        command: AdminConfigCommands,
+    },
+    /// Migrate all user accounts to another server
+    Admin {
+        #[command(subcommand)]
+        command: AdminArchiveCommands,
+    },
+    /// Show whether the daemon is healthy and ready to serve payments
+    Status,
+    /// Print the node id of this CLI to add to ADMIN_NODE_IDS of a remote daemon
+    Identity,
 }
 
 #[derive(Subcommand, Debug)]
@@ -44,6 +141,13 @@ enum AdminLdkCommands {
     NodeId,
     /// Get node balances
     Balances,
+    /// Compare our assets with the balances owed to users
+    Solvency {
+        /// Publish a new Merkle sum tree of all user balances that users can
+        /// verify the inclusion of their balance against
+        #[arg(long)]
+        publish: bool,
+    },
     /// On-chain operations
     Onchain {
         #[command(subcommand)]
@@ -59,6 +163,8 @@ enum AdminLdkCommands {
         #[command(subcommand)]
         command: AdminPeerCommands,
     },
+    /// Summarize the fees earned by forwarding payments
+    Forwarding(ForwardingReportRequest),
 }
 
 #[derive(Subcommand, Debug)]
@@ -79,8 +185,27 @@ enum AdminChannelCommands {
     Close(CloseChannelRequest),
     /// List all Lightning channels
     List,
+    /// Update the forwarding fees and limits of a channel, unset values are kept
+    Config(UpdateChannelConfigRequest),
     /// Request a channel from the LSP
     Request(RequestChannelRequest),
+    /// Buy a channel from the LSPS1 liquidity source over the lightning connection
+    Order {
+        #[command(subcommand)]
+        command: AdminChannelOrderCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum AdminChannelOrderCommands {
+    /// Request a quote for an inbound channel, showing the fee before paying
+    Create(CreateChannelOrderRequest),
+    /// Pay a quoted order from our channels or on-chain wallet
+    Pay(PayChannelOrderRequest),
+    /// Fetch the current state of an order from the LSP
+    Refresh(ChannelOrderIdRequest),
+    /// List all channel orders
+    List,
 }
 
 #[derive(Subcommand, Debug)]
@@ -95,12 +220,87 @@ enum AdminPeerCommands {
 
 #[derive(Subcommand, Debug)]
 enum AdminUserCommands {
-    /// Generate an invite code
-    Invite(InviteRequest),
+    /// Generate an invite code or manage existing invites
+    #[command(args_conflicts_with_subcommands = true)]
+    Invite {
+        #[command(flatten)]
+        args: InviteRequest,
+        #[command(subcommand)]
+        command: Option<AdminInviteCommands>,
+    },
     /// Recover a user
     Recover(RecoverRequest),
     /// List all users
     List,
+    /// Freeze, suspend, delete or reactivate a user
+    Status(SetUserStatusRequest),
+    /// Credit or debit a user's balance with a memo shown in their history
+    Adjust(AdjustBalanceRequest),
+}
+
+#[derive(Subcommand, Debug)]
+enum AdminInviteCommands {
+    /// List all invites with their number of registered users
+    List,
+    /// Show an invite with its code and registered users
+    Show(InviteIdRequest),
+    /// Revoke an invite such that no more users can register with it
+    Revoke(InviteIdRequest),
+}
+
+#[derive(Subcommand, Debug)]
+enum AdminSecretCommands {
+    /// Export the daemon's secret key as a BIP39 mnemonic
+    Export,
+    /// Rotate the daemon's secret key, clients follow to the new node id after a restart
+    Rotate,
+    /// Restore the daemon's secret key from a BIP39 mnemonic before its first startup
+    Restore(RestoreSecretArgs),
+}
+
+#[derive(Subcommand, Debug)]
+enum AdminAuditCommands {
+    /// List audit log entries of operator actions, newest first
+    List(AuditListRequest),
+}
+
+#[derive(Args, Debug)]
+struct RestoreSecretArgs {
+    /// The daemon's data directory to restore the secret key into
+    #[arg(long, env = "PUNCTURE_DATA_DIR")]
+    puncture_data_dir: PathBuf,
+    /// Encrypt the restored secret key with this passphrase
+    #[arg(long, env = "PUNCTURE_SECRET_PASSPHRASE", hide_env_values = true)]
+    passphrase: Option<String>,
+    /// The 24 word mnemonic
+    #[arg(required = true, value_name = "WORD")]
+    words: Vec<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum AdminBackupCommands {
+    /// Back up the daemon's and LDK's databases into the backup directory
+    Create,
+    /// List the retained backups
+    List,
+}
+
+#[derive(Subcommand, Debug)]
+enum AdminConfigCommands {
+    /// Re-read the config file and apply its fee and limit settings without a restart
+    Reload,
+}
+
+#[derive(Subcommand, Debug)]
+enum AdminArchiveCommands {
+    /// Print a signed archive of all users, invites, balances and payment history
+    Export,
+    /// Import an archive into a daemon without users that has restored the
+    /// secret key of the exporting daemon
+    Import {
+        /// The archive file written by the export command
+        path: PathBuf,
+    },
 }
 
 fn main() -> Result<()> {
@@ -108,67 +308,215 @@ fn main() -> Result<()> {
 
     match cli.command {
         AdminCommands::Ldk { command } => match command {
-            AdminLdkCommands::NodeId => request(cli.cli_port, ROUTE_LDK_NODE_ID, ()),
-            AdminLdkCommands::Balances => request(cli.cli_port, ROUTE_LDK_BALANCES, ()),
+            AdminLdkCommands::NodeId => request(&cli.connection, ROUTE_LDK_NODE_ID, ()),
+            AdminLdkCommands::Balances => request(&cli.connection, ROUTE_LDK_BALANCES, ()),
+            AdminLdkCommands::Solvency { publish } => match publish {
+                true => request(&cli.connection, ROUTE_LDK_SOLVENCY_PUBLISH, ()),
+                false => request(&cli.connection, ROUTE_LDK_SOLVENCY, ()),
+            },
             AdminLdkCommands::Onchain { command } => match command {
                 AdminOnchainCommands::Receive => {
-                    request(cli.cli_port, ROUTE_LDK_ONCHAIN_RECEIVE, ())
+                    request(&cli.connection, ROUTE_LDK_ONCHAIN_RECEIVE, ())
                 }
                 AdminOnchainCommands::Send(req) => {
-                    request(cli.cli_port, ROUTE_LDK_ONCHAIN_SEND, req)
+                    request(&cli.connection, ROUTE_LDK_ONCHAIN_SEND, req)
                 }
                 AdminOnchainCommands::Drain(req) => {
-                    request(cli.cli_port, ROUTE_LDK_ONCHAIN_DRAIN, req)
+                    request(&cli.connection, ROUTE_LDK_ONCHAIN_DRAIN, req)
                 }
             },
             AdminLdkCommands::Channel { command } => match command {
                 AdminChannelCommands::Open(req) => {
-                    request(cli.cli_port, ROUTE_LDK_CHANNEL_OPEN, req)
+                    request(&cli.connection, ROUTE_LDK_CHANNEL_OPEN, req)
                 }
                 AdminChannelCommands::Close(req) => {
-                    request(cli.cli_port, ROUTE_LDK_CHANNEL_CLOSE, req)
+                    request(&cli.connection, ROUTE_LDK_CHANNEL_CLOSE, req)
+                }
+                AdminChannelCommands::List => request(&cli.connection, ROUTE_LDK_CHANNEL_LIST, ()),
+                AdminChannelCommands::Config(req) => {
+                    request(&cli.connection, ROUTE_LDK_CHANNEL_CONFIG, req)
                 }
-                AdminChannelCommands::List => request(cli.cli_port, ROUTE_LDK_CHANNEL_LIST, ()),
                 AdminChannelCommands::Request(req) => {
-                    request(cli.cli_port, ROUTE_LDK_CHANNEL_REQUEST, req)
+                    request(&cli.connection, ROUTE_LDK_CHANNEL_REQUEST, req)
                 }
+                AdminChannelCommands::Order { command } => match command {
+                    AdminChannelOrderCommands::Create(req) => {
+                        request(&cli.connection, ROUTE_LDK_CHANNEL_ORDER_CREATE, req)
+                    }
+                    AdminChannelOrderCommands::Pay(req) => {
+                        request(&cli.connection, ROUTE_LDK_CHANNEL_ORDER_PAY, req)
+                    }
+                    AdminChannelOrderCommands::Refresh(req) => {
+                        request(&cli.connection, ROUTE_LDK_CHANNEL_ORDER_REFRESH, req)
+                    }
+                    AdminChannelOrderCommands::List => {
+                        request(&cli.connection, ROUTE_LDK_CHANNEL_ORDER_LIST, ())
+                    }
+                },
             },
             AdminLdkCommands::Peer { command } => match command {
                 AdminPeerCommands::Connect(req) => {
-                    request(cli.cli_port, ROUTE_LDK_PEER_CONNECT, req)
+                    request(&cli.connection, ROUTE_LDK_PEER_CONNECT, req)
                 }
                 AdminPeerCommands::Disconnect(req) => {
-                    request(cli.cli_port, ROUTE_LDK_PEER_DISCONNECT, req)
+                    request(&cli.connection, ROUTE_LDK_PEER_DISCONNECT, req)
                 }
-                AdminPeerCommands::List => request(cli.cli_port, ROUTE_LDK_PEER_LIST, ()),
+                AdminPeerCommands::List => request(&cli.connection, ROUTE_LDK_PEER_LIST, ()),
             },
+            AdminLdkCommands::Forwarding(req) => {
+                request(&cli.connection, ROUTE_LDK_FORWARDING_REPORT, req)
+            }
         },
         AdminCommands::User { command } => match command {
-            AdminUserCommands::Invite(req) => request(cli.cli_port, ROUTE_USER_INVITE, req),
-            AdminUserCommands::Recover(req) => request(cli.cli_port, ROUTE_USER_RECOVER, req),
-            AdminUserCommands::List => request(cli.cli_port, ROUTE_USER_LIST, ()),
+            AdminUserCommands::Invite { args, command } => match command {
+                None => request(&cli.connection, ROUTE_USER_INVITE, args),
+                Some(AdminInviteCommands::List) => {
+                    request(&cli.connection, ROUTE_USER_INVITE_LIST, ())
+                }
+                Some(AdminInviteCommands::Show(req)) => {
+                    request(&cli.connection, ROUTE_USER_INVITE_SHOW, req)
+                }
+                Some(AdminInviteCommands::Revoke(req)) => {
+                    request(&cli.connection, ROUTE_USER_INVITE_REVOKE, req)
+                }
+            },
+            AdminUserCommands::Recover(req) => request(&cli.connection, ROUTE_USER_RECOVER, req),
+            AdminUserCommands::List => request(&cli.connection, ROUTE_USER_LIST, ()),
+            AdminUserCommands::Status(req) => request(&cli.connection, ROUTE_USER_STATUS, req),
+            AdminUserCommands::Adjust(req) => request(&cli.connection, ROUTE_USER_ADJUST, req),
+        },
+        AdminCommands::Secret { command } => match command {
+            AdminSecretCommands::Export => request(&cli.connection, ROUTE_SECRET_EXPORT, ()),
+            AdminSecretCommands::Rotate => request(&cli.connection, ROUTE_SECRET_ROTATE, ()),
+            AdminSecretCommands::Restore(args) => restore_secret(args),
+        },
+        AdminCommands::Backup { command } => match command {
+            AdminBackupCommands::Create => request(&cli.connection, ROUTE_BACKUP_CREATE, ()),
+            AdminBackupCommands::List => request(&cli.connection, ROUTE_BACKUP_LIST, ()),
         },
+        AdminCommands::Config { command } => match command {
+            AdminConfigCommands::Reload => request(&cli.connection, ROUTE_CONFIG_RELOAD, ()),
+        },
+        AdminCommands::Admin { command } => match command {
+            AdminArchiveCommands::Export => request(&cli.connection, ROUTE_ADMIN_EXPORT, ()),
+            AdminArchiveCommands::Import { path } => {
+                let archive: SignedArchive = serde_json::from_str(
+                    &std::fs::read_to_string(&path).context("Failed to read archive")?,
+                )
+                .context("Failed to parse archive")?;
+
+                request(&cli.connection, ROUTE_ADMIN_IMPORT, archive)
+            }
+        },
+        AdminCommands::Audit { command } => match command {
+            AdminAuditCommands::List(req) => request(&cli.connection, ROUTE_AUDIT_LIST, req),
+        },
+        AdminCommands::Status => request(&cli.connection, ROUTE_STATUS, ()),
+        AdminCommands::Identity => identity(&cli.connection),
     }
 }
 
-fn request<R: Serialize>(port: u16, route: &str, request: R) -> Result<()> {
-    let response = reqwest::blocking::Client::new()
-        .post(format!("http://127.0.0.1:{port}{route}").as_str())
-        .json(&serde_json::to_value(request)?)
-        .send()
-        .context("Failed to connect to daemon")?;
+fn restore_secret(args: RestoreSecretArgs) -> Result<()> {
+    std::fs::create_dir_all(&args.puncture_data_dir)
+        .context("Failed to create puncture data directory")?;
+
+    let secret_key = secret::restore(
+        &args.puncture_data_dir,
+        &args.words.join(" "),
+        args.passphrase.as_deref(),
+    )?;
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&serde_json::json!({
+            "node_id": secret_key.public().to_string(),
+        }))?
+    );
 
-    ensure!(
-        response.status().is_success(),
-        "API error ({}): {}",
-        response.status().as_u16(),
-        response.text()?
+    Ok(())
+}
+
+fn identity(connection: &ConnectionArgs) -> Result<()> {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&serde_json::json!({
+            "node_id": connection.identity()?.public().to_string(),
+        }))?
     );
 
+    Ok(())
+}
+
+fn request<R: Serialize>(connection: &ConnectionArgs, route: &str, request: R) -> Result<()> {
+    let (status, body) = match connection.daemon {
+        Some(node_id) => {
+            remote_request(connection, node_id, route, serde_json::to_value(request)?)?
+        }
+        None => {
+            let response = reqwest::blocking::Client::new()
+                .post(format!("http://127.0.0.1:{}{route}", connection.cli_port).as_str())
+                .bearer_auth(connection.token()?)
+                .json(&serde_json::to_value(request)?)
+                .send()
+                .context("Failed to connect to daemon")?;
+
+            (response.status().as_u16(), response.text()?)
+        }
+    };
+
+    ensure!((200..300).contains(&status), "API error ({status}): {body}");
+
     println!(
         "{}",
-        serde_json::to_string_pretty(&response.json::<Value>()?)?
+        serde_json::to_string_pretty(&serde_json::from_str::<Value>(&body)?)?
     );
 
     Ok(())
 }
+
+/// Call a CLI route of a remote daemon over the admin ALPN of its iroh endpoint
+fn remote_request(
+    connection: &ConnectionArgs,
+    node_id: iroh::NodeId,
+    route: &str,
+    request: Value,
+) -> Result<(u16, String)> {
+    let secret_key = connection.identity()?;
+
+    let request = serde_json::to_vec(&AdminRpcRequest {
+        route: route.to_string(),
+        request,
+    })?;
+
+    tokio::runtime::Runtime::new()?.block_on(async {
+        let endpoint = Endpoint::builder()
+            .secret_key(secret_key)
+            .discovery_n0()
+            .discovery_dht()
+            .relay_mode(RelayMode::Disabled)
+            .bind()
+            .await?;
+
+        let connection = endpoint
+            .connect(node_id, ADMIN_ALPN)
+            .await
+            .context("Failed to connect to daemon")?;
+
+        let (mut send, mut recv) = connection.open_bi().await?;
+
+        send.write_all(&request).await?;
+
+        send.finish()?;
+
+        let response: AdminRpcResponse = serde_json::from_slice(
+            &recv
+                .read_to_end(100_000_000)
+                .await
+                .context("Daemon rejected the connection, is this CLI an admin?")?,
+        )?;
+
+        endpoint.close().await;
+
+        Ok((response.status, response.body))
+    })
+}
@@ -1,4 +1,5 @@
 use bitcoin::address::NetworkUnchecked;
+use bitcoin::hashes::{Hash, HashEngine, sha256};
 use bitcoin::{Address, Network, Txid};
 use lightning_invoice::Bolt11Invoice;
 use serde::{Deserialize, Serialize};
@@ -12,6 +13,10 @@ pub const ENDPOINT_BOLT12_SEND: &str = "bolt12_send";
 pub const ENDPOINT_ONCHAIN_SEND: &str = "onchain_send";
 pub const ENDPOINT_SET_RECOVERY_NAME: &str = "set_recovery_name";
 pub const ENDPOINT_RECOVER: &str = "recover";
+pub const ENDPOINT_CREATE_RECOVERY_CODE: &str = "create_recovery_code";
+pub const ENDPOINT_LIABILITY_PROOF: &str = "liability_proof";
+pub const ENDPOINT_RECEIVABLE_MAX: &str = "receivable_max";
+pub const ENDPOINT_MIGRATION: &str = "migration";
 
 /// A helper struct for JSON-RPC requests over Iroh
 #[derive(Serialize, Deserialize, Debug)]
@@ -24,11 +29,11 @@ pub struct ClientRpcRequest<R> {
 pub struct Payment {
     /// The payment id
     pub id: String,
-    /// The payment type, "send" or "receive"
+    /// The payment type, "send", "receive" or "adjustment"
     pub payment_type: String,
     /// Wether the payment was processed after the client connected
     pub is_live: bool,
-    /// The amount in millisatoshis
+    /// The amount in millisatoshis, negative for adjustments debiting the user
     pub amount_msat: i64,
     /// The fee in millisatoshis
     pub fee_msat: i64,
@@ -85,12 +90,60 @@ pub struct Bolt11ReceiveRequest {
     pub amount_msat: u32,
     /// Description of the invoice
     pub description: String,
+    /// Maximum fee in millisatoshis the user accepts for the LSP to open a
+    /// just-in-time channel if the daemon lacks inbound liquidity
+    #[serde(default)]
+    pub max_lsp_fee_msat: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Bolt11ReceiveResponse {
     /// The generated invoice
     pub invoice: Bolt11Invoice,
+    /// Fee in millisatoshis the LSP deducts from the amount credited to the
+    /// user if the invoice is paid via a just-in-time channel
+    #[serde(default)]
+    pub lsp_fee_msat: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReceivableMaxResponse {
+    /// The largest amount in millisatoshis the daemon can currently receive
+    pub receivable_max_msat: u64,
+}
+
+const INSUFFICIENT_INBOUND_LIQUIDITY: &str =
+    "Insufficient inbound liquidity, the daemon can currently receive at most ";
+
+/// The error returned when the daemon cannot receive the requested amount.
+/// Errors are sent as messages, so clients recover it via `parse`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InsufficientInboundLiquidity {
+    /// The largest amount in millisatoshis the daemon can currently receive
+    pub receivable_max_msat: u64,
+}
+
+impl InsufficientInboundLiquidity {
+    pub fn parse(error: &str) -> Option<Self> {
+        error
+            .strip_prefix(INSUFFICIENT_INBOUND_LIQUIDITY)?
+            .strip_suffix(" msat")?
+            .parse()
+            .ok()
+            .map(|receivable_max_msat| Self {
+                receivable_max_msat,
+            })
+    }
+}
+
+impl std::fmt::Display for InsufficientInboundLiquidity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{INSUFFICIENT_INBOUND_LIQUIDITY}{} msat",
+            self.receivable_max_msat
+        )
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -141,6 +194,9 @@ pub struct SetRecoveryNameRequest {
 pub struct RecoverRequest {
     /// The recovery id
     pub recovery_id: String,
+    /// The PIN protecting a user created recovery code
+    #[serde(default)]
+    pub pin: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -148,3 +204,113 @@ pub struct RecoverResponse {
     /// The recovered balance in millisatoshis
     pub balance_msat: u64,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateRecoveryCodeRequest {
+    /// An optional PIN required in addition to the recovery code
+    pub pin: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateRecoveryCodeResponse {
+    /// The encoded recovery code, replacing any previously created one
+    pub recovery_code: String,
+}
+
+/// A node of the Merkle sum tree over all user balances, committing to the
+/// sum of the balances below it such that the operator cannot hide liabilities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiabilityNode {
+    /// The hash of the node
+    pub hash: sha256::Hash,
+    /// The sum of all balances below the node in millisatoshis
+    pub sum_msat: u64,
+}
+
+impl LiabilityNode {
+    /// The leaf of a user, blinded by a nonce such that sibling leaves in
+    /// other users' proofs do not reveal the user's public key and balance.
+    pub fn leaf(user_pk: &str, nonce: &str, balance_msat: u64) -> Self {
+        let mut engine = sha256::Hash::engine();
+
+        engine.input(b"puncture/liability/leaf/");
+        engine.input(user_pk.as_bytes());
+        engine.input(nonce.as_bytes());
+        engine.input(&balance_msat.to_be_bytes());
+
+        Self {
+            hash: sha256::Hash::from_engine(engine),
+            sum_msat: balance_msat,
+        }
+    }
+
+    /// Empty node padding levels with an odd number of nodes
+    pub fn empty() -> Self {
+        Self {
+            hash: sha256::Hash::all_zeros(),
+            sum_msat: 0,
+        }
+    }
+
+    /// Combine two nodes into their parent, returning None if the sum overflows
+    pub fn parent(left: &Self, right: &Self) -> Option<Self> {
+        let sum_msat = left.sum_msat.checked_add(right.sum_msat)?;
+
+        let mut engine = sha256::Hash::engine();
+
+        engine.input(b"puncture/liability/node/");
+        engine.input(left.hash.as_byte_array());
+        engine.input(&left.sum_msat.to_be_bytes());
+        engine.input(right.hash.as_byte_array());
+        engine.input(&right.sum_msat.to_be_bytes());
+
+        Some(Self {
+            hash: sha256::Hash::from_engine(engine),
+            sum_msat,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiabilityProofStep {
+    /// The sibling of the current node on the path to the root
+    pub sibling: LiabilityNode,
+    /// Wether the sibling is the left child of the parent
+    pub sibling_is_left: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiabilityProof {
+    /// The root of the tree as published by the operator
+    pub root: LiabilityNode,
+    /// The user's balance at the time the tree was published in millisatoshis
+    pub balance_msat: u64,
+    /// The nonce blinding the user's leaf
+    pub nonce: String,
+    /// The siblings on the path from the user's leaf to the root
+    pub path: Vec<LiabilityProofStep>,
+    /// The time the tree was published
+    pub created_at: i64,
+}
+
+impl LiabilityProof {
+    /// Verify that the user's balance is included in the total liabilities
+    /// committed to by the root
+    pub fn verify(&self, user_pk: &str) -> Result<(), String> {
+        let mut node = LiabilityNode::leaf(user_pk, &self.nonce, self.balance_msat);
+
+        for step in &self.path {
+            node = match step.sibling_is_left {
+                true => LiabilityNode::parent(&step.sibling, &node),
+                false => LiabilityNode::parent(&node, &step.sibling),
+            }
+            .ok_or("Liability sum overflows".to_string())?;
+        }
+
+        if node != self.root {
+            return Err("Balance is not included in the published liabilities".to_string());
+        }
+
+        Ok(())
+    }
+}
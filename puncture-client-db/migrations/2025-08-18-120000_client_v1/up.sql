@@ -0,0 +1,3 @@
+-- Your SQL goes here
+
+ALTER TABLE daemon ADD COLUMN secret_key TEXT;
@@ -9,4 +9,7 @@ pub struct DaemonRecord {
     pub network: String,
     pub name: String,
     pub created_at: i64,
+    /// The hex encoded identity used with this daemon, none for installs
+    /// that registered with the master secret before per-daemon identities
+    pub secret_key: Option<String>,
 }
@@ -6,5 +6,6 @@ diesel::table! {
         network -> Text,
         name -> Text,
         created_at -> BigInt,
+        secret_key -> Nullable<Text>,
     }
 }
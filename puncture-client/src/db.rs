@@ -1,4 +1,5 @@
-use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl, SqliteConnection};
+use bitcoin::hex::DisplayHex;
+use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SqliteConnection};
 
 use puncture_client_core::RegisterResponse;
 use puncture_client_db::models::DaemonRecord;
@@ -8,6 +9,7 @@ use puncture_core::unix_time;
 pub async fn save_daemon(
     conn: &mut SqliteConnection,
     node_id: iroh::NodeId,
+    secret_key: &iroh::SecretKey,
     config: RegisterResponse,
 ) {
     diesel::insert_or_ignore_into(daemon::table)
@@ -16,11 +18,23 @@ pub async fn save_daemon(
             network: config.network.to_string(),
             name: config.name,
             created_at: unix_time(),
+            secret_key: Some(secret_key.to_bytes().as_hex().to_string()),
         })
         .execute(conn)
         .expect("Failed to save daemon");
 }
 
+pub async fn get_daemon(
+    conn: &mut SqliteConnection,
+    node_id: iroh::NodeId,
+) -> Option<DaemonRecord> {
+    daemon::table
+        .filter(daemon::node_id.eq(node_id.to_string()))
+        .first::<DaemonRecord>(conn)
+        .optional()
+        .expect("Failed to query daemon")
+}
+
 pub async fn list_daemons(conn: &mut SqliteConnection) -> Vec<DaemonRecord> {
     daemon::dsl::daemon
         .load::<DaemonRecord>(conn)
@@ -32,3 +46,19 @@ pub async fn delete_daemon(conn: &mut SqliteConnection, node_id: iroh::NodeId) {
         .execute(conn)
         .expect("Failed to remove daemon");
 }
+
+/// Move a daemon to the node id it has migrated to, keeping our identity
+pub async fn migrate_daemon(
+    conn: &mut SqliteConnection,
+    old_node_id: iroh::NodeId,
+    new_node_id: iroh::NodeId,
+) {
+    if get_daemon(conn, new_node_id).await.is_some() {
+        return delete_daemon(conn, old_node_id).await;
+    }
+
+    diesel::update(daemon::table.filter(daemon::node_id.eq(old_node_id.to_string())))
+        .set(daemon::node_id.eq(new_node_id.to_string()))
+        .execute(conn)
+        .expect("Failed to migrate daemon");
+}
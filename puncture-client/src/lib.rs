@@ -1,11 +1,13 @@
 mod db;
 
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::Arc;
 use std::time::Duration;
 
-use anyhow::Context;
+use anyhow::{Context, bail, ensure};
 use bitcoin::address::NetworkUnchecked;
 use bitcoin::{Address, Txid};
 use iroh::Endpoint;
@@ -13,36 +15,80 @@ use iroh::endpoint::{Connection, RelayMode};
 use lightning::offers::offer::Offer;
 use lightning_invoice::Bolt11Invoice;
 use serde::{Serialize, de::DeserializeOwned};
-use tokio::{sync::watch, task::AbortHandle};
-use tracing::warn;
+use tokio::sync::{Mutex, watch};
+use tokio::task::AbortHandle;
+use tracing::{info, warn};
 
 use puncture_client_core::{
     AppEvent, Bolt11ReceiveRequest, Bolt11ReceiveResponse, Bolt11SendRequest,
-    Bolt12ReceiveResponse, Bolt12SendRequest, ClientRpcRequest, ENDPOINT_BOLT11_RECEIVE,
-    ENDPOINT_BOLT11_SEND, ENDPOINT_BOLT12_RECEIVE, ENDPOINT_BOLT12_SEND, ENDPOINT_ONCHAIN_SEND,
-    ENDPOINT_RECOVER, ENDPOINT_REGISTER, ENDPOINT_SET_RECOVERY_NAME, OnchainSendRequest,
-    OnchainSendResponse, RecoverRequest, RecoverResponse, RegisterRequest, RegisterResponse,
-    SetRecoveryNameRequest,
+    Bolt12ReceiveResponse, Bolt12SendRequest, ClientRpcRequest, CreateRecoveryCodeRequest,
+    CreateRecoveryCodeResponse, ENDPOINT_BOLT11_RECEIVE, ENDPOINT_BOLT11_SEND,
+    ENDPOINT_BOLT12_RECEIVE, ENDPOINT_BOLT12_SEND, ENDPOINT_CREATE_RECOVERY_CODE,
+    ENDPOINT_LIABILITY_PROOF, ENDPOINT_MIGRATION, ENDPOINT_ONCHAIN_SEND, ENDPOINT_RECEIVABLE_MAX,
+    ENDPOINT_RECOVER, ENDPOINT_REGISTER, ENDPOINT_SET_RECOVERY_NAME, LiabilityProof,
+    OnchainSendRequest, OnchainSendResponse, ReceivableMaxResponse, RecoverRequest,
+    RecoverResponse, RegisterRequest, RegisterResponse, SetRecoveryNameRequest,
 };
+use puncture_client_db::models::DaemonRecord;
 use puncture_core::db::Database;
-use puncture_core::{InviteCode, RecoveryCode, secret};
+use puncture_core::{InviteCode, NodeIdMigration, PunctureCode, RecoveryCode, secret};
 
 pub struct PunctureClient {
-    endpoint: Endpoint,
+    /// The master secret all per-daemon identities are derived from
+    secret_key: iroh::SecretKey,
+    /// The endpoints bound for each identity in use, keyed by public key
+    endpoints: Arc<Mutex<HashMap<iroh::PublicKey, Endpoint>>>,
     db: Database,
 }
 
 impl PunctureClient {
-    /// Create a new puncture client
-    pub async fn new(data_dir: String) -> Self {
+    /// Create a new puncture client, failing if the secret key file is
+    /// corrupted or encrypted
+    pub async fn new(data_dir: String) -> Result<Self, String> {
         let data_dir = PathBuf::from(data_dir);
 
-        fs::create_dir_all(&data_dir).expect("Failed to create data directory");
+        fs::create_dir_all(&data_dir)
+            .map_err(|e| format!("Failed to create data directory: {e}"))?;
+
+        let secret_key = secret::read_or_generate(&data_dir, None)
+            .map_err(|e| format!("Failed to read secret key: {e:#}"))?;
+
+        let db = Database::new(&data_dir, puncture_client_db::MIGRATIONS, 3)
+            .map_err(|e| format!("Failed to setup database: {e:#}"))?;
+
+        Ok(Self {
+            secret_key,
+            endpoints: Arc::new(Mutex::new(HashMap::new())),
+            db,
+        })
+    }
+
+    /// Restore a puncture client from a mnemonic backup. Daemons have to be
+    /// registered again, which recovers the same account on each of them.
+    pub async fn restore(data_dir: String, mnemonic: String) -> Result<Self, String> {
+        fs::create_dir_all(&data_dir)
+            .map_err(|e| format!("Failed to create data directory: {e}"))?;
+
+        secret::restore(&PathBuf::from(&data_dir), &mnemonic, None).map_err(|e| e.to_string())?;
+
+        Self::new(data_dir).await
+    }
+
+    /// Export the master secret as a BIP39 mnemonic for backup
+    pub fn mnemonic(&self) -> String {
+        secret::to_mnemonic(&self.secret_key)
+    }
 
-        let secret_key = secret::read_or_generate(&data_dir);
+    /// Returns the endpoint for the given identity, binding it on first use
+    async fn endpoint(&self, secret_key: iroh::SecretKey) -> Endpoint {
+        let mut endpoints = self.endpoints.lock().await;
+
+        if let Some(endpoint) = endpoints.get(&secret_key.public()) {
+            return endpoint.clone();
+        }
 
         let endpoint = Endpoint::builder()
-            .secret_key(secret_key)
+            .secret_key(secret_key.clone())
             .discovery_n0()
             .discovery_dht()
             .relay_mode(RelayMode::Disabled)
@@ -50,16 +96,35 @@ impl PunctureClient {
             .await
             .expect("Failed to bind iroh endpoint");
 
-        let db = Database::new(&data_dir, puncture_client_db::MIGRATIONS, 3)
-            .expect("Failed to setup database");
+        endpoints.insert(secret_key.public(), endpoint.clone());
+
+        endpoint
+    }
 
-        Self { endpoint, db }
+    /// Returns the identity used with a daemon. Daemons registered before
+    /// per-daemon identities were introduced keep using the master secret.
+    fn daemon_secret(&self, record: &DaemonRecord) -> iroh::SecretKey {
+        match &record.secret_key {
+            Some(secret_key) => {
+                iroh::SecretKey::from_str(secret_key).expect("Invalid secret key in database")
+            }
+            None => self.secret_key.clone(),
+        }
     }
 
     pub async fn register(&self, invite: InviteCode) -> Result<PunctureConnection, String> {
-        let connection = self
-            .endpoint
-            .connect(invite.node_id(), b"puncture")
+        let mut conn = self.db.get_connection().await;
+
+        let secret_key = match db::get_daemon(&mut conn, invite.node_id()).await {
+            Some(record) => self.daemon_secret(&record),
+            None => secret::derive_daemon_secret(&self.secret_key, &invite.node_id()),
+        };
+
+        drop(conn);
+
+        let endpoint = self.endpoint(secret_key.clone()).await;
+
+        let (node_id, connection) = connect(&endpoint, invite.node_id())
             .await
             .map_err(|_| "Failed to connect".to_string())?;
 
@@ -75,35 +140,41 @@ impl PunctureClient {
 
         db::save_daemon(
             &mut *self.db.get_connection().await,
-            invite.node_id(),
+            node_id,
+            &secret_key,
             response,
         )
         .await;
 
-        Ok(PunctureConnection::new(
-            self.endpoint.clone(),
-            invite.node_id(),
-        ))
+        Ok(PunctureConnection::new(endpoint, node_id, self.db.clone()))
     }
 
     pub async fn list_daemons(&self) -> Vec<Daemon> {
-        db::list_daemons(&mut *self.db.get_connection().await)
-            .await
-            .into_iter()
-            .map(|daemon| Daemon {
-                endpoint: self.endpoint.clone(),
-                node_id: iroh::NodeId::from_str(&daemon.node_id).unwrap(),
-                name: daemon.name,
-            })
-            .collect()
+        let records = db::list_daemons(&mut *self.db.get_connection().await).await;
+
+        let mut daemons = Vec::new();
+
+        for record in records {
+            daemons.push(Daemon {
+                endpoint: self.endpoint(self.daemon_secret(&record)).await,
+                node_id: iroh::NodeId::from_str(&record.node_id).unwrap(),
+                name: record.name,
+                db: self.db.clone(),
+            });
+        }
+
+        daemons
     }
 
     pub async fn delete_daemon(&self, daemon: Daemon) {
         db::delete_daemon(&mut *self.db.get_connection().await, daemon.node_id).await;
     }
 
+    /// The public key of the master secret, which only identifies the user to
+    /// daemons registered before per-daemon identities were introduced
+    #[deprecated(note = "every daemon sees a different identity, use Daemon::user_pk")]
     pub async fn user_pk(&self) -> String {
-        self.endpoint.secret_key().public().to_string()
+        self.secret_key.public().to_string()
     }
 }
 
@@ -111,6 +182,7 @@ pub struct Daemon {
     endpoint: Endpoint,
     node_id: iroh::NodeId,
     name: String,
+    db: Database,
 }
 
 impl Daemon {
@@ -118,8 +190,13 @@ impl Daemon {
         self.name.clone()
     }
 
+    /// The public key identifying the user to this daemon
+    pub fn user_pk(&self) -> String {
+        self.endpoint.secret_key().public().to_string()
+    }
+
     pub fn connect(&self) -> PunctureConnection {
-        PunctureConnection::new(self.endpoint.clone(), self.node_id)
+        PunctureConnection::new(self.endpoint.clone(), self.node_id, self.db.clone())
     }
 }
 
@@ -130,6 +207,8 @@ pub struct PunctureConnection {
     receiver: watch::Receiver<Option<Connection>>,
     /// A handle to the background task managing the connection
     handle: AbortHandle,
+    /// The public key identifying the user to this daemon
+    user_pk: String,
 }
 
 impl Drop for PunctureConnection {
@@ -140,12 +219,18 @@ impl Drop for PunctureConnection {
 
 impl PunctureConnection {
     /// Create a new puncture connection
-    pub fn new(endpoint: Endpoint, node_id: iroh::NodeId) -> Self {
+    pub fn new(endpoint: Endpoint, node_id: iroh::NodeId, db: Database) -> Self {
         let (sender, receiver) = watch::channel(None);
 
-        let handle = tokio::spawn(reconnect(endpoint, node_id, sender)).abort_handle();
+        let user_pk = endpoint.secret_key().public().to_string();
+
+        let handle = tokio::spawn(reconnect(endpoint, node_id, db, sender)).abort_handle();
 
-        Self { receiver, handle }
+        Self {
+            receiver,
+            handle,
+            user_pk,
+        }
     }
 
     /// Make a request to the daemon
@@ -172,12 +257,33 @@ impl PunctureConnection {
             Bolt11ReceiveRequest {
                 amount_msat,
                 description,
+                max_lsp_fee_msat: None,
             },
         )
         .await
         .map(|response: Bolt11ReceiveResponse| response.invoice)
     }
 
+    /// Create a bolt11 invoice that is paid via a just-in-time channel if the
+    /// daemon lacks inbound liquidity. The response discloses the fee the LSP
+    /// deducts from the amount received, which must not exceed the given limit
+    pub async fn bolt11_receive_jit(
+        &self,
+        amount_msat: u32,
+        description: String,
+        max_lsp_fee_msat: u64,
+    ) -> Result<Bolt11ReceiveResponse, String> {
+        self.request(
+            ENDPOINT_BOLT11_RECEIVE,
+            Bolt11ReceiveRequest {
+                amount_msat,
+                description,
+                max_lsp_fee_msat: Some(max_lsp_fee_msat),
+            },
+        )
+        .await
+    }
+
     /// Send a bolt11 payment
     pub async fn bolt11_send(
         &self,
@@ -263,30 +369,79 @@ impl PunctureConnection {
         .await
     }
 
-    /// Recover a balance from a recovery code
-    pub async fn recover(&self, recovery_code: RecoveryCode) -> Result<u64, String> {
+    /// Recover a balance from a recovery code, the PIN is only required for
+    /// recovery codes created with one by the user
+    pub async fn recover(
+        &self,
+        recovery_code: RecoveryCode,
+        pin: Option<String>,
+    ) -> Result<u64, String> {
         self.request(
             ENDPOINT_RECOVER,
             RecoverRequest {
                 recovery_id: recovery_code.id(),
+                pin,
             },
         )
         .await
         .map(|response: RecoverResponse| response.balance_msat)
     }
+
+    /// Create a recovery code for this user, optionally protected by a PIN, to
+    /// move the balance to a new device without involving the operator. This
+    /// invalidates any recovery code previously created by this user. The code
+    /// can only be used once and expires after a year.
+    pub async fn create_recovery_code(&self, pin: Option<String>) -> Result<RecoveryCode, String> {
+        let response: CreateRecoveryCodeResponse = self
+            .request(
+                ENDPOINT_CREATE_RECOVERY_CODE,
+                CreateRecoveryCodeRequest { pin },
+            )
+            .await?;
+
+        PunctureCode::decode(&response.recovery_code)
+            .map_err(|e| e.to_string())?
+            .to_recovery()
+    }
+
+    /// Get the largest amount the daemon can currently receive, invoices above
+    /// it fail with an `InsufficientInboundLiquidity` error
+    pub async fn receivable_max(&self) -> Result<u64, String> {
+        self.request(ENDPOINT_RECEIVABLE_MAX, ())
+            .await
+            .map(|response: ReceivableMaxResponse| response.receivable_max_msat)
+    }
+
+    /// Fetch the proof that our balance is included in the liabilities last
+    /// published by the operator and verify it. The root of the returned proof
+    /// should be compared with the one the operator published out of band.
+    pub async fn liability_proof(&self) -> Result<LiabilityProof, String> {
+        let proof: LiabilityProof = self.request(ENDPOINT_LIABILITY_PROOF, ()).await?;
+
+        proof.verify(&self.user_pk)?;
+
+        Ok(proof)
+    }
 }
 
 /// Background task that maintains a single connection to the daemon
 async fn reconnect(
     endpoint: Endpoint,
-    node_id: iroh::NodeId,
+    mut node_id: iroh::NodeId,
+    db: Database,
     sender: watch::Sender<Option<Connection>>,
 ) {
     let mut backoff = backoff_durations();
 
     loop {
-        match endpoint.connect(node_id, b"puncture").await {
-            Ok(connection) => {
+        match connect(&endpoint, node_id).await {
+            Ok((new_node_id, connection)) => {
+                if new_node_id != node_id {
+                    db::migrate_daemon(&mut *db.get_connection().await, node_id, new_node_id).await;
+
+                    node_id = new_node_id;
+                }
+
                 sender.send(Some(connection.clone())).ok();
 
                 connection.closed().await;
@@ -304,6 +459,45 @@ async fn reconnect(
     }
 }
 
+/// Connect to a daemon, following the signed migration statements of daemons
+/// that have rotated their secret key. Returns the current node id.
+async fn connect(
+    endpoint: &Endpoint,
+    mut node_id: iroh::NodeId,
+) -> anyhow::Result<(iroh::NodeId, Connection)> {
+    for _ in 0..10 {
+        let connection = endpoint.connect(node_id, b"puncture").await?;
+
+        // Daemons predating key rotation do not know the method and reply
+        // with an error, current daemons reply with no migration statement
+        let migration = match request_json::<_, Option<NodeIdMigration>>(
+            connection.clone(),
+            ENDPOINT_MIGRATION,
+            (),
+        )
+        .await?
+        {
+            Ok(Some(migration)) => migration,
+            Ok(None) | Err(..) => return Ok((node_id, connection)),
+        };
+
+        ensure!(
+            migration.old_node_id == node_id,
+            "Migration statement is for a different node id"
+        );
+
+        migration.verify()?;
+
+        info!(?migration, "Following daemon node id migration");
+
+        connection.close(0u32.into(), b"migrated");
+
+        node_id = migration.new_node_id;
+    }
+
+    bail!("Too many node id migrations")
+}
+
 fn backoff_durations() -> impl Iterator<Item = Duration> {
     (1..).map(|i| Duration::from_millis(std::cmp::min(i * i * 100, 10_000)))
 }
@@ -4,7 +4,10 @@ use anyhow::{Context, Result};
 use diesel::Connection;
 use diesel::sqlite::SqliteConnection;
 use diesel_migrations::{EmbeddedMigrations, MigrationHarness};
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+/// Name of the SQLite database file within the data directory
+pub const FILE_NAME: &str = "puncture_data.sqlite";
 
 #[derive(Clone)]
 pub struct Database {
@@ -13,7 +16,7 @@ pub struct Database {
 
 impl Database {
     pub fn new(data_dir: &Path, migrations: EmbeddedMigrations, _max_size: u32) -> Result<Self> {
-        let file_path = data_dir.join("puncture_data.sqlite").display().to_string();
+        let file_path = data_dir.join(FILE_NAME).display().to_string();
 
         let mut conn = SqliteConnection::establish(&file_path)
             .context("Error establishing connection to database")?;
@@ -29,4 +32,9 @@ impl Database {
     pub async fn get_connection(&self) -> tokio::sync::MutexGuard<'_, SqliteConnection> {
         self.conn.lock().await
     }
+
+    /// Lock the connection for use on a blocking thread
+    pub async fn get_owned_connection(&self) -> OwnedMutexGuard<SqliteConnection> {
+        self.conn.clone().lock_owned().await
+    }
 }
@@ -3,7 +3,7 @@ pub mod secret;
 
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use anyhow::{Result, ensure};
+use anyhow::{Context, Result, ensure};
 use bitcoin::hex::{DisplayHex, FromHex};
 use iroh::NodeId;
 use serde::{Deserialize, Serialize};
@@ -82,3 +82,60 @@ impl PunctureCode {
         Ok(postcard::from_bytes(&Vec::from_hex(&s[3..])?)?)
     }
 }
+
+/// A statement signed with a daemon's previous secret key announcing the node
+/// id it has rotated to, such that clients can follow the daemon to its new key.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct NodeIdMigration {
+    /// The node id the daemon used before the rotation
+    pub old_node_id: NodeId,
+    /// The node id the daemon uses after the rotation
+    pub new_node_id: NodeId,
+    /// The creation time of the statement
+    pub created_at: i64,
+    /// The hex encoded signature by the old node id over the statement
+    pub signature: String,
+}
+
+impl NodeIdMigration {
+    pub fn new(old_secret_key: &iroh::SecretKey, new_node_id: NodeId) -> Self {
+        let old_node_id = old_secret_key.public();
+
+        let created_at = unix_time();
+
+        let signature = old_secret_key
+            .sign(&Self::message(&old_node_id, &new_node_id, created_at))
+            .to_bytes()
+            .as_hex()
+            .to_string();
+
+        Self {
+            old_node_id,
+            new_node_id,
+            created_at,
+            signature,
+        }
+    }
+
+    /// Verify that the statement was signed by the old node id
+    pub fn verify(&self) -> Result<()> {
+        let signature = <[u8; 64]>::from_hex(&self.signature).context("Invalid signature")?;
+
+        self.old_node_id
+            .verify(
+                &Self::message(&self.old_node_id, &self.new_node_id, self.created_at),
+                &iroh_base::Signature::from_bytes(&signature),
+            )
+            .context("Invalid signature")
+    }
+
+    fn message(old_node_id: &NodeId, new_node_id: &NodeId, created_at: i64) -> Vec<u8> {
+        [
+            b"puncture-migration".as_slice(),
+            old_node_id.as_bytes(),
+            new_node_id.as_bytes(),
+            &created_at.to_be_bytes(),
+        ]
+        .concat()
+    }
+}
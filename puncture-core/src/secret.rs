@@ -1,28 +1,315 @@
-use std::{fs, path::Path};
+use std::fs::{self, File, OpenOptions, Permissions};
+use std::io::{ErrorKind, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
 
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use argon2::Argon2;
+use bip39::Mnemonic;
+use bitcoin::hashes::{Hash, HashEngine, Hmac, HmacEngine, sha256};
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
 use rand::Rng;
 
-/// Read an existing secret key from file, or generate and save a new one
-pub fn read_or_generate(data_dir: &Path) -> iroh::SecretKey {
-    let file_path = data_dir.join("puncture_secret.key");
+const FILE_NAME: &str = "puncture_secret.key";
+
+/// Prefix of the versioned secret file format. Files without it are legacy
+/// files containing the 32 raw bytes of the secret key.
+const MAGIC: &[u8; 4] = b"PCTK";
+
+const VERSION: u8 = 1;
+
+const KIND_PLAINTEXT: u8 = 0;
+
+/// The secret is encrypted with ChaCha20Poly1305 under a key derived from the
+/// passphrase with Argon2id and followed by the salt and nonce.
+const KIND_ENCRYPTED: u8 = 1;
+
+/// Read an existing secret key from file, or generate and save a new one. If
+/// a passphrase is given the secret is encrypted at rest, and existing files
+/// in the legacy or unencrypted format are migrated to the encrypted format.
+pub fn read_or_generate(data_dir: &Path, passphrase: Option<&str>) -> Result<iroh::SecretKey> {
+    let file_path = data_dir.join(FILE_NAME);
 
     if file_path.exists() {
-        let bytes = fs::read(file_path)
-            .expect("Failed to read secret file")
-            .try_into()
-            .unwrap();
+        // Files written before the permissions were hardened may still be
+        // readable by other users of the system
+        fs::set_permissions(&file_path, Permissions::from_mode(0o600))
+            .context("Failed to restrict secret file permissions")?;
 
-        return iroh::SecretKey::from_bytes(&bytes);
+        let bytes = fs::read(&file_path).context("Failed to read secret file")?;
+
+        let (secret, encrypted) = decode(&bytes, passphrase)?;
+
+        if (passphrase.is_some() && !encrypted) || !bytes.starts_with(MAGIC) {
+            migrate(&file_path, &bytes, &encode(&secret, passphrase)?)?;
+        }
+
+        return Ok(iroh::SecretKey::from_bytes(&secret));
     }
 
     let secret: [u8; 32] = rand::rng().random();
 
-    fs::write(&file_path, secret).expect("Failed to write secret file");
+    create(&file_path, &encode(&secret, passphrase)?)?;
 
-    iroh::SecretKey::from_bytes(&secret)
+    Ok(iroh::SecretKey::from_bytes(&secret))
+}
+
+/// Replace a secret file in an outdated format, keeping a copy of the original
+/// file until the replacement has been synced to disk
+fn migrate(file_path: &Path, old: &[u8], new: &[u8]) -> Result<()> {
+    let backup_path = file_path.with_extension("key.bak");
+
+    write(&backup_path, old)?;
+
+    write(file_path, new)?;
+
+    fs::remove_file(&backup_path).context("Failed to remove secret file backup")?;
+
+    sync_dir(file_path)
+}
+
+/// Read the current secret key from file. After a rotation this differs from
+/// the key the running daemon was started with.
+pub fn read(data_dir: &Path, passphrase: Option<&str>) -> Result<iroh::SecretKey> {
+    let bytes = fs::read(data_dir.join(FILE_NAME)).context("Failed to read secret file")?;
+
+    Ok(iroh::SecretKey::from_bytes(&decode(&bytes, passphrase)?.0))
 }
 
 /// Check if the this is the first startup of the daemon
 pub fn exists(data_dir: &Path) -> bool {
-    data_dir.join("puncture_secret.key").exists()
+    data_dir.join(FILE_NAME).exists()
+}
+
+/// Encode a secret key as a 24 word BIP39 mnemonic for backup
+pub fn to_mnemonic(secret_key: &iroh::SecretKey) -> String {
+    Mnemonic::from_entropy(&secret_key.to_bytes())
+        .expect("32 bytes are valid BIP39 entropy")
+        .to_string()
+}
+
+/// Restore the secret key from a BIP39 mnemonic, refusing to overwrite an
+/// existing secret since that would silently replace the current identity
+pub fn restore(
+    data_dir: &Path,
+    mnemonic: &str,
+    passphrase: Option<&str>,
+) -> Result<iroh::SecretKey> {
+    let file_path = data_dir.join(FILE_NAME);
+
+    ensure!(!file_path.exists(), "A secret key already exists");
+
+    let secret: [u8; 32] = Mnemonic::parse(mnemonic)
+        .context("Invalid mnemonic")?
+        .to_entropy()
+        .try_into()
+        .ok()
+        .context("Mnemonic must consist of 24 words")?;
+
+    create(&file_path, &encode(&secret, passphrase)?)?;
+
+    Ok(iroh::SecretKey::from_bytes(&secret))
+}
+
+/// Move the current secret key aside such that it can still be loaded by its
+/// node id and replace it with a newly generated one. Returns the old and new key.
+pub fn rotate(
+    data_dir: &Path,
+    passphrase: Option<&str>,
+) -> Result<(iroh::SecretKey, iroh::SecretKey)> {
+    let old = read(data_dir, passphrase)?;
+
+    fs::create_dir_all(data_dir.join("retired")).context("Failed to create retired directory")?;
+
+    write(
+        &retired_path(data_dir, &old.public()),
+        &encode(&old.to_bytes(), passphrase)?,
+    )?;
+
+    let new: [u8; 32] = rand::rng().random();
+
+    write(&data_dir.join(FILE_NAME), &encode(&new, passphrase)?)?;
+
+    Ok((old, iroh::SecretKey::from_bytes(&new)))
+}
+
+/// Read a secret key that has been retired by a rotation
+pub fn read_retired(
+    data_dir: &Path,
+    node_id: &iroh::NodeId,
+    passphrase: Option<&str>,
+) -> Result<iroh::SecretKey> {
+    let bytes =
+        fs::read(retired_path(data_dir, node_id)).context("Failed to read retired secret")?;
+
+    Ok(iroh::SecretKey::from_bytes(&decode(&bytes, passphrase)?.0))
+}
+
+fn retired_path(data_dir: &Path, node_id: &iroh::NodeId) -> PathBuf {
+    data_dir.join("retired").join(format!("{node_id}.key"))
+}
+
+/// Decode a secret file, returning the secret and whether it was encrypted
+fn decode(bytes: &[u8], passphrase: Option<&str>) -> Result<([u8; 32], bool)> {
+    if !bytes.starts_with(MAGIC) {
+        let secret = bytes
+            .try_into()
+            .ok()
+            .context("Secret file has an invalid length")?;
+
+        return Ok((secret, false));
+    }
+
+    ensure!(bytes.len() > MAGIC.len() + 2, "Secret file is truncated");
+
+    let (header, payload) = bytes.split_at(MAGIC.len() + 2);
+
+    ensure!(
+        header[MAGIC.len()] == VERSION,
+        "Unsupported secret file version {}",
+        header[MAGIC.len()]
+    );
+
+    match header[MAGIC.len() + 1] {
+        KIND_PLAINTEXT => {
+            let secret = payload
+                .try_into()
+                .ok()
+                .context("Secret file has an invalid length")?;
+
+            Ok((secret, false))
+        }
+        KIND_ENCRYPTED => {
+            let passphrase = passphrase.context("Secret file is encrypted, passphrase required")?;
+
+            ensure!(payload.len() > 28, "Secret file has an invalid length");
+
+            let (salt, payload) = payload.split_at(16);
+
+            let (nonce, ciphertext) = payload.split_at(12);
+
+            let secret = cipher(passphrase, salt)?
+                .decrypt(
+                    Nonce::from_slice(nonce),
+                    Payload {
+                        msg: ciphertext,
+                        aad: header,
+                    },
+                )
+                .map_err(|_| anyhow!("Failed to decrypt secret file, wrong passphrase?"))?
+                .try_into()
+                .ok()
+                .context("Secret file has an invalid length")?;
+
+            Ok((secret, true))
+        }
+        kind => bail!("Unknown secret file kind {kind}"),
+    }
+}
+
+fn encode(secret: &[u8; 32], passphrase: Option<&str>) -> Result<Vec<u8>> {
+    let Some(passphrase) = passphrase else {
+        return Ok([MAGIC.as_slice(), &[VERSION, KIND_PLAINTEXT], secret].concat());
+    };
+
+    let header = [MAGIC.as_slice(), &[VERSION, KIND_ENCRYPTED]].concat();
+
+    let salt: [u8; 16] = rand::rng().random();
+
+    let nonce: [u8; 12] = rand::rng().random();
+
+    let ciphertext = cipher(passphrase, &salt)?
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: secret,
+                aad: &header,
+            },
+        )
+        .map_err(|_| anyhow!("Failed to encrypt secret"))?;
+
+    Ok([header.as_slice(), &salt, &nonce, &ciphertext].concat())
+}
+
+fn cipher(passphrase: &str, salt: &[u8]) -> Result<ChaCha20Poly1305> {
+    let mut key = [0; 32];
+
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("Failed to derive key from passphrase: {e}"))?;
+
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+/// Atomically replace the secret file with one only readable by the owner. The
+/// file and the directory entry are synced to disk before returning, such that
+/// a crash cannot leave us with an empty or truncated secret.
+fn write(file_path: &Path, bytes: &[u8]) -> Result<()> {
+    let tmp_path = write_tmp(file_path, bytes)?;
+
+    fs::rename(&tmp_path, file_path).context("Failed to replace secret file")?;
+
+    sync_dir(file_path)
+}
+
+/// Write a new secret file, failing if the file exists. The hard link is
+/// created atomically, unlike a rename it never replaces an existing file.
+fn create(file_path: &Path, bytes: &[u8]) -> Result<()> {
+    let tmp_path = write_tmp(file_path, bytes)?;
+
+    let result = fs::hard_link(&tmp_path, file_path);
+
+    fs::remove_file(&tmp_path).context("Failed to remove temporary secret file")?;
+
+    match result {
+        Ok(()) => sync_dir(file_path),
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => bail!("A secret key already exists"),
+        Err(e) => Err(e).context("Failed to create secret file"),
+    }
+}
+
+/// Write the bytes to a temporary file next to the secret file and sync it
+fn write_tmp(file_path: &Path, bytes: &[u8]) -> Result<PathBuf> {
+    let tmp_path = file_path.with_extension("tmp");
+
+    fs::remove_file(&tmp_path).ok();
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&tmp_path)
+        .context("Failed to create secret file")?;
+
+    file.write_all(bytes)
+        .context("Failed to write secret file")?;
+
+    file.sync_all().context("Failed to sync secret file")?;
+
+    Ok(tmp_path)
+}
+
+/// Sync the directory containing the file such that a rename is durable
+fn sync_dir(file_path: &Path) -> Result<()> {
+    File::open(
+        file_path
+            .parent()
+            .context("Secret file has no parent directory")?,
+    )
+    .and_then(|dir| dir.sync_all())
+    .context("Failed to sync secret directory")
+}
+
+/// Derive the identity a client presents to a specific daemon from its master
+/// secret, such that daemons cannot correlate a user across operators.
+pub fn derive_daemon_secret(master: &iroh::SecretKey, node_id: &iroh::NodeId) -> iroh::SecretKey {
+    let mut engine = HmacEngine::<sha256::Hash>::new(&master.to_bytes());
+
+    engine.input(b"puncture/daemon/");
+
+    engine.input(node_id.as_bytes());
+
+    iroh::SecretKey::from_bytes(&Hmac::from_engine(engine).to_byte_array())
 }
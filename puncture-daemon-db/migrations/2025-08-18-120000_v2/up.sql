@@ -0,0 +1,6 @@
+CREATE TABLE migration (
+    old_node_id TEXT PRIMARY KEY NOT NULL,
+    new_node_id TEXT NOT NULL,
+    signature TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
@@ -0,0 +1,7 @@
+CREATE TABLE recovery_code (
+    id_hash TEXT PRIMARY KEY NOT NULL,
+    user_pk TEXT NOT NULL UNIQUE,
+    pin_hash TEXT,
+    failed_attempts BIGINT NOT NULL,
+    created_at BIGINT NOT NULL
+);
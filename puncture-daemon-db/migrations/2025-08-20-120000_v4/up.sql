@@ -0,0 +1,11 @@
+CREATE TABLE audit_log (
+    id TEXT PRIMARY KEY NOT NULL,
+    actor TEXT NOT NULL,
+    action TEXT NOT NULL,
+    request TEXT NOT NULL,
+    status INTEGER NOT NULL,
+    result TEXT,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX audit_log_created_at ON audit_log (created_at);
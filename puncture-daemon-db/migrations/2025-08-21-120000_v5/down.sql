@@ -0,0 +1 @@
+ALTER TABLE invite DROP COLUMN revoked_at;
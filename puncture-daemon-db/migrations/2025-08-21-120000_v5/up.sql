@@ -0,0 +1 @@
+ALTER TABLE invite ADD COLUMN revoked_at BIGINT;
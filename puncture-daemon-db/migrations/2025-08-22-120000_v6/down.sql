@@ -0,0 +1,2 @@
+ALTER TABLE user DROP COLUMN status_reason;
+ALTER TABLE user DROP COLUMN status;
@@ -0,0 +1,2 @@
+ALTER TABLE user ADD COLUMN status TEXT NOT NULL DEFAULT 'active';
+ALTER TABLE user ADD COLUMN status_reason TEXT;
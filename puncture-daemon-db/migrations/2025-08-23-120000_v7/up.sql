@@ -0,0 +1,7 @@
+CREATE TABLE adjustment (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_pk TEXT NOT NULL,
+    amount_msat BIGINT NOT NULL,
+    memo TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
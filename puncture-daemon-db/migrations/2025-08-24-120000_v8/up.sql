@@ -0,0 +1,17 @@
+CREATE TABLE channel_order (
+    id TEXT PRIMARY KEY NOT NULL,
+    lsp_node_id TEXT NOT NULL,
+    lsp_balance_sat BIGINT NOT NULL,
+    client_balance_sat BIGINT NOT NULL,
+    channel_expiry_blocks INTEGER NOT NULL,
+    announce_channel BOOLEAN NOT NULL,
+    fee_total_sat BIGINT NOT NULL,
+    order_total_sat BIGINT NOT NULL,
+    invoice TEXT,
+    onchain_address TEXT,
+    payment_state TEXT NOT NULL,
+    payment TEXT,
+    funding_outpoint TEXT,
+    created_at BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL
+);
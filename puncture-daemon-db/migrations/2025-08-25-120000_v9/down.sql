@@ -0,0 +1,2 @@
+DROP INDEX idx_liquidity_decision_created_at;
+DROP TABLE liquidity_decision;
@@ -0,0 +1,14 @@
+CREATE TABLE liquidity_decision (
+    id TEXT PRIMARY KEY NOT NULL,
+    action TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    dry_run BOOLEAN NOT NULL,
+    outcome TEXT,
+    inbound_capacity_msat BIGINT NOT NULL,
+    outbound_capacity_msat BIGINT NOT NULL,
+    liabilities_msat BIGINT NOT NULL,
+    onchain_balance_sats BIGINT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX idx_liquidity_decision_created_at ON liquidity_decision(created_at);
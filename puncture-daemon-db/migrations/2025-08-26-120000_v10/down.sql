@@ -0,0 +1,2 @@
+DROP INDEX idx_forward_created_at;
+DROP TABLE forward;
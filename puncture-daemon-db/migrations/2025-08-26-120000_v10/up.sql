@@ -0,0 +1,14 @@
+CREATE TABLE forward (
+    id TEXT PRIMARY KEY NOT NULL,
+    prev_channel_id TEXT NOT NULL,
+    next_channel_id TEXT NOT NULL,
+    prev_node_id TEXT,
+    next_node_id TEXT,
+    fee_earned_msat BIGINT,
+    skimmed_fee_msat BIGINT,
+    amount_forwarded_msat BIGINT,
+    claim_from_onchain_tx BOOLEAN NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX idx_forward_created_at ON forward(created_at);
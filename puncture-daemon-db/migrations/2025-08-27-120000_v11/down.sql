@@ -0,0 +1,2 @@
+ALTER TABLE recovery_code DROP COLUMN last_failed_at;
+ALTER TABLE recovery_code DROP COLUMN expires_at;
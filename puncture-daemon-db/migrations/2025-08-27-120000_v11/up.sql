@@ -0,0 +1,4 @@
+ALTER TABLE recovery_code ADD COLUMN expires_at BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE recovery_code ADD COLUMN last_failed_at BIGINT;
+
+UPDATE recovery_code SET expires_at = created_at + 31536000000;
@@ -0,0 +1,2 @@
+-- Redacted audit results cannot be restored
+SELECT 1;
@@ -0,0 +1,2 @@
+UPDATE audit_log SET result = 'redacted'
+WHERE action IN ('/user/invite', '/user/recover') AND status BETWEEN 200 AND 299;
@@ -0,0 +1,2 @@
+DROP TABLE liability_leaf;
+DROP TABLE liability_publication;
@@ -0,0 +1,12 @@
+CREATE TABLE liability_publication (
+    created_at BIGINT PRIMARY KEY NOT NULL,
+    root_hash TEXT NOT NULL,
+    liabilities_msat BIGINT NOT NULL
+);
+
+CREATE TABLE liability_leaf (
+    user_pk TEXT PRIMARY KEY NOT NULL,
+    leaf_index BIGINT NOT NULL,
+    nonce TEXT NOT NULL,
+    balance_msat BIGINT NOT NULL
+);
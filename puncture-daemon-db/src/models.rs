@@ -1,6 +1,7 @@
 use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
 
-#[derive(Queryable, Selectable, Insertable, Debug, Clone)]
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize, Debug, Clone)]
 #[diesel(table_name = crate::schema::user)]
 #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
 pub struct User {
@@ -8,9 +9,11 @@ pub struct User {
     pub invite_id: String,
     pub recovery_name: Option<String>,
     pub created_at: i64,
+    pub status: String,
+    pub status_reason: Option<String>,
 }
 
-#[derive(Queryable, Selectable, Insertable, Debug, Clone)]
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize, Debug, Clone)]
 #[diesel(table_name = crate::schema::invite)]
 #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
 pub struct InviteRecord {
@@ -18,6 +21,7 @@ pub struct InviteRecord {
     pub user_limit: i64,
     pub expires_at: i64,
     pub created_at: i64,
+    pub revoked_at: Option<i64>,
 }
 
 #[derive(Queryable, Selectable, Insertable, Debug, Clone)]
@@ -33,7 +37,7 @@ pub struct InvoiceRecord {
     pub created_at: i64,
 }
 
-#[derive(Queryable, Selectable, Insertable, Debug, Clone)]
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize, Debug, Clone)]
 #[diesel(table_name = crate::schema::receive)]
 #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
 pub struct ReceiveRecord {
@@ -45,7 +49,7 @@ pub struct ReceiveRecord {
     pub created_at: i64,
 }
 
-#[derive(Queryable, Selectable, Insertable, Debug, Clone)]
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize, Debug, Clone)]
 #[diesel(table_name = crate::schema::send)]
 #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
 pub struct SendRecord {
@@ -82,3 +86,122 @@ pub struct RecoveryRecord {
     pub expires_at: i64,
     pub created_at: i64,
 }
+
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::recovery_code)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct RecoveryCodeRecord {
+    pub id_hash: String,
+    pub user_pk: String,
+    pub pin_hash: Option<String>,
+    pub failed_attempts: i64,
+    pub created_at: i64,
+    pub expires_at: i64,
+    pub last_failed_at: Option<i64>,
+}
+
+#[derive(Queryable, Selectable, Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::migration)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct MigrationRecord {
+    pub old_node_id: String,
+    pub new_node_id: String,
+    pub signature: String,
+    pub created_at: i64,
+}
+
+#[derive(Queryable, Selectable, Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::audit_log)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct AuditLogRecord {
+    pub id: String,
+    pub actor: String,
+    pub action: String,
+    pub request: String,
+    pub status: i32,
+    pub result: Option<String>,
+    pub created_at: i64,
+}
+
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::adjustment)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct AdjustmentRecord {
+    pub id: String,
+    pub user_pk: String,
+    pub amount_msat: i64,
+    pub memo: String,
+    pub created_at: i64,
+}
+
+#[derive(Queryable, Selectable, Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::channel_order)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ChannelOrderRecord {
+    pub id: String,
+    pub lsp_node_id: String,
+    pub lsp_balance_sat: i64,
+    pub client_balance_sat: i64,
+    pub channel_expiry_blocks: i32,
+    pub announce_channel: bool,
+    pub fee_total_sat: i64,
+    pub order_total_sat: i64,
+    pub invoice: Option<String>,
+    pub onchain_address: Option<String>,
+    pub payment_state: String,
+    pub payment: Option<String>,
+    pub funding_outpoint: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Queryable, Selectable, Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::liquidity_decision)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct LiquidityDecisionRecord {
+    pub id: String,
+    pub action: String,
+    pub reason: String,
+    pub dry_run: bool,
+    pub outcome: Option<String>,
+    pub inbound_capacity_msat: i64,
+    pub outbound_capacity_msat: i64,
+    pub liabilities_msat: i64,
+    pub onchain_balance_sats: i64,
+    pub created_at: i64,
+}
+
+#[derive(Queryable, Selectable, Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::forward)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ForwardRecord {
+    pub id: String,
+    pub prev_channel_id: String,
+    pub next_channel_id: String,
+    pub prev_node_id: Option<String>,
+    pub next_node_id: Option<String>,
+    pub fee_earned_msat: Option<i64>,
+    pub skimmed_fee_msat: Option<i64>,
+    pub amount_forwarded_msat: Option<i64>,
+    pub claim_from_onchain_tx: bool,
+    pub created_at: i64,
+}
+
+#[derive(Queryable, Selectable, Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::liability_publication)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct LiabilityPublicationRecord {
+    pub created_at: i64,
+    pub root_hash: String,
+    pub liabilities_msat: i64,
+}
+
+#[derive(Queryable, Selectable, Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::liability_leaf)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct LiabilityLeafRecord {
+    pub user_pk: String,
+    pub leaf_index: i64,
+    pub nonce: String,
+    pub balance_msat: i64,
+}
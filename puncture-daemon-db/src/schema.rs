@@ -6,6 +6,7 @@ diesel::table! {
         user_limit -> BigInt,
         expires_at -> BigInt,
         created_at -> BigInt,
+        revoked_at -> Nullable<BigInt>,
     }
 }
 
@@ -67,15 +68,142 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    recovery_code (id_hash) {
+        id_hash -> Text,
+        user_pk -> Text,
+        pin_hash -> Nullable<Text>,
+        failed_attempts -> BigInt,
+        created_at -> BigInt,
+        expires_at -> BigInt,
+        last_failed_at -> Nullable<BigInt>,
+    }
+}
+
+diesel::table! {
+    migration (old_node_id) {
+        old_node_id -> Text,
+        new_node_id -> Text,
+        signature -> Text,
+        created_at -> BigInt,
+    }
+}
+
 diesel::table! {
     user (user_pk) {
         user_pk -> Text,
         invite_id -> Text,
         recovery_name -> Nullable<Text>,
         created_at -> BigInt,
+        status -> Text,
+        status_reason -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    audit_log (id) {
+        id -> Text,
+        actor -> Text,
+        action -> Text,
+        request -> Text,
+        status -> Integer,
+        result -> Nullable<Text>,
+        created_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    adjustment (id) {
+        id -> Text,
+        user_pk -> Text,
+        amount_msat -> BigInt,
+        memo -> Text,
+        created_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    channel_order (id) {
+        id -> Text,
+        lsp_node_id -> Text,
+        lsp_balance_sat -> BigInt,
+        client_balance_sat -> BigInt,
+        channel_expiry_blocks -> Integer,
+        announce_channel -> Bool,
+        fee_total_sat -> BigInt,
+        order_total_sat -> BigInt,
+        invoice -> Nullable<Text>,
+        onchain_address -> Nullable<Text>,
+        payment_state -> Text,
+        payment -> Nullable<Text>,
+        funding_outpoint -> Nullable<Text>,
+        created_at -> BigInt,
+        updated_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    liquidity_decision (id) {
+        id -> Text,
+        action -> Text,
+        reason -> Text,
+        dry_run -> Bool,
+        outcome -> Nullable<Text>,
+        inbound_capacity_msat -> BigInt,
+        outbound_capacity_msat -> BigInt,
+        liabilities_msat -> BigInt,
+        onchain_balance_sats -> BigInt,
+        created_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    forward (id) {
+        id -> Text,
+        prev_channel_id -> Text,
+        next_channel_id -> Text,
+        prev_node_id -> Nullable<Text>,
+        next_node_id -> Nullable<Text>,
+        fee_earned_msat -> Nullable<BigInt>,
+        skimmed_fee_msat -> Nullable<BigInt>,
+        amount_forwarded_msat -> Nullable<BigInt>,
+        claim_from_onchain_tx -> Bool,
+        created_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    liability_publication (created_at) {
+        created_at -> BigInt,
+        root_hash -> Text,
+        liabilities_msat -> BigInt,
+    }
+}
+
+diesel::table! {
+    liability_leaf (user_pk) {
+        user_pk -> Text,
+        leaf_index -> BigInt,
+        nonce -> Text,
+        balance_msat -> BigInt,
     }
 }
 
 diesel::allow_tables_to_appear_in_same_query!(
-    invite, invoice, receive, send, offer, recovery, user,
+    adjustment,
+    audit_log,
+    channel_order,
+    forward,
+    invite,
+    invoice,
+    liability_leaf,
+    liability_publication,
+    liquidity_decision,
+    receive,
+    send,
+    offer,
+    recovery,
+    recovery_code,
+    migration,
+    user,
 );
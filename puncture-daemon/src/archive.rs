@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use bitcoin::hex::{DisplayHex, FromHex};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use puncture_cli_core::{ImportResponse, SignedArchive};
+use puncture_core::unix_time;
+use puncture_daemon_db::models::{
+    AdjustmentRecord, InviteRecord, ReceiveRecord, RecoveryCodeRecord, SendRecord, User,
+};
+
+use crate::AppState;
+
+/// Incremented whenever the archive format changes incompatibly
+const ARCHIVE_VERSION: u32 = 1;
+
+/// All records required to serve our users from another server
+#[derive(Serialize, Deserialize)]
+pub struct Accounts {
+    pub users: Vec<User>,
+    pub invites: Vec<InviteRecord>,
+    pub receives: Vec<ReceiveRecord>,
+    pub sends: Vec<SendRecord>,
+    pub adjustments: Vec<AdjustmentRecord>,
+    pub recovery_codes: Vec<RecoveryCodeRecord>,
+}
+
+impl Accounts {
+    /// Compute the user balances from the ledger the same way the database does
+    fn balances(&self) -> BTreeMap<String, u64> {
+        let mut balances: BTreeMap<String, i64> = self
+            .users
+            .iter()
+            .map(|user| (user.user_pk.clone(), 0))
+            .collect();
+
+        for receive in &self.receives {
+            *balances.entry(receive.user_pk.clone()).or_default() += receive.amount_msat;
+        }
+
+        for adjustment in &self.adjustments {
+            *balances.entry(adjustment.user_pk.clone()).or_default() += adjustment.amount_msat;
+        }
+
+        for send in self.sends.iter().filter(|send| send.status != "failed") {
+            *balances.entry(send.user_pk.clone()).or_default() -= send.amount_msat + send.fee_msat;
+        }
+
+        balances
+            .into_iter()
+            .map(|(user_pk, balance)| (user_pk, balance.max(0) as u64))
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Archive {
+    version: u32,
+    created_at: i64,
+    accounts: Accounts,
+    /// The balance of every user at the time of the export
+    balances: BTreeMap<String, u64>,
+    /// The sum of all balances at the time of the export
+    liabilities_msat: u64,
+}
+
+/// Export all user accounts into an archive signed with our secret key
+pub async fn export(state: &AppState) -> Result<SignedArchive, String> {
+    let mut conn = state.db.get_connection().await;
+
+    if crate::db::count_all_pending_sends(&mut conn).await > 0 {
+        return Err("Wait for all pending payments to complete before exporting".to_string());
+    }
+
+    let balances: BTreeMap<String, u64> = crate::db::list_user_balances(&mut conn)
+        .await
+        .into_iter()
+        .collect();
+
+    let liabilities_msat = balances.values().sum();
+
+    let archive = Archive {
+        version: ARCHIVE_VERSION,
+        created_at: unix_time(),
+        accounts: crate::db::load_accounts(&mut conn).await,
+        balances,
+        liabilities_msat,
+    };
+
+    let archive = serde_json::to_string(&archive).expect("Failed to serialize archive");
+
+    let signature = state
+        .secret_key
+        .sign(&message(&archive))
+        .to_bytes()
+        .as_hex()
+        .to_string();
+
+    info!(?liabilities_msat, "Exported user accounts");
+
+    Ok(SignedArchive {
+        node_id: state.node_id.to_string(),
+        archive,
+        signature,
+    })
+}
+
+/// Import an archive exported by a daemon with the same secret key into a
+/// fresh database. Users derive their identity from our node id, such that an
+/// archive is useless to a daemon with a different secret key.
+pub async fn import(state: &AppState, signed: SignedArchive) -> Result<ImportResponse, String> {
+    let node_id = iroh::NodeId::from_str(&signed.node_id).map_err(|_| "Invalid node id")?;
+
+    if node_id != state.node_id {
+        return Err(format!(
+            "Archive was exported by node id {node_id}, restore its secret key before importing"
+        ));
+    }
+
+    let signature = <[u8; 64]>::from_hex(&signed.signature).map_err(|_| "Invalid signature")?;
+
+    node_id
+        .verify(
+            &message(&signed.archive),
+            &iroh_base::Signature::from_bytes(&signature),
+        )
+        .map_err(|_| "Invalid signature")?;
+
+    let archive: Archive = serde_json::from_str(&signed.archive)
+        .map_err(|e| format!("Failed to deserialize archive: {e}"))?;
+
+    if archive.version != ARCHIVE_VERSION {
+        return Err(format!("Unsupported archive version {}", archive.version));
+    }
+
+    if archive.accounts.balances() != archive.balances {
+        return Err("User balances do not match the archive's ledger".to_string());
+    }
+
+    if archive.balances.values().sum::<u64>() != archive.liabilities_msat {
+        return Err("User balances do not match the archive's total liabilities".to_string());
+    }
+
+    let solvency = crate::solvency::report(state).await;
+
+    if solvency.user_count > 0 {
+        return Err("Accounts can only be imported into a daemon without users".to_string());
+    }
+
+    if solvency.assets_msat < archive.liabilities_msat {
+        return Err(format!(
+            "Insufficient liquidity, our assets of {} msat do not cover the archive's liabilities of {} msat",
+            solvency.assets_msat, archive.liabilities_msat
+        ));
+    }
+
+    crate::db::insert_accounts(&mut *state.db.get_connection().await, &archive.accounts).await?;
+
+    info!(
+        users = archive.accounts.users.len(),
+        liabilities_msat = archive.liabilities_msat,
+        "Imported user accounts"
+    );
+
+    Ok(ImportResponse {
+        user_count: archive.accounts.users.len() as u64,
+        invite_count: archive.accounts.invites.len() as u64,
+        payment_count: (archive.accounts.receives.len()
+            + archive.accounts.sends.len()
+            + archive.accounts.adjustments.len()) as u64,
+        liabilities_msat: archive.liabilities_msat,
+    })
+}
+
+fn message(archive: &str) -> Vec<u8> {
+    [b"puncture-archive".as_slice(), archive.as_bytes()].concat()
+}
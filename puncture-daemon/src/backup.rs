@@ -0,0 +1,353 @@
+use std::collections::BTreeMap;
+use std::fs::{self, Permissions};
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, ensure};
+use bitcoin::hashes::{Hash, sha256};
+use diesel::{Connection, QueryableByName, RunQueryDsl, SqliteConnection, sql_query};
+use serde::{Deserialize, Serialize};
+use tokio::sync::OwnedMutexGuard;
+use tracing::{info, warn};
+
+use puncture_cli_core::{BackupInfo, ListBackupsResponse};
+use puncture_core::{secret, unix_time};
+
+use crate::{AppState, Args, secret_passphrase};
+
+/// Incremented whenever the backup layout changes incompatibly
+const BACKUP_VERSION: u32 = 1;
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// The SQLite database LDK Node keeps all channel and wallet state in
+const LDK_DB_FILE: &str = "ldk_node_data.sqlite";
+
+/// The seed LDK Node derives its node id and on-chain wallet from
+const LDK_SEED_FILE: &str = "keys_seed";
+
+const BACKUP_PREFIX: &str = "backup-";
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    /// The iroh node id of the daemon that created the backup
+    node_id: String,
+    created_at: i64,
+    /// The SHA256 hash of every file in the backup
+    files: BTreeMap<String, String>,
+}
+
+#[derive(QueryableByName)]
+struct IntegrityCheck {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    integrity_check: String,
+}
+
+fn backup_dir(args: &Args) -> PathBuf {
+    args.backup_dir
+        .clone()
+        .unwrap_or(args.puncture_data_dir.join("backups"))
+}
+
+/// Create an online backup of our database and LDK's database. We hold the
+/// lock on our single database connection while both snapshots are taken, such
+/// that no payment can be recorded in one database but not the other. Backups
+/// contain LDK's seed and are therefore only readable by the owner.
+pub async fn create(state: &AppState) -> Result<BackupInfo> {
+    let created_at = unix_time();
+
+    let backup_dir = backup_dir(&state.args);
+
+    let name = format!("{BACKUP_PREFIX}{created_at}");
+
+    let tmp_path = backup_dir.join(format!(".{name}.tmp"));
+
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&tmp_path)
+        .context("Failed to create backup directory")?;
+
+    let conn = state.db.get_owned_connection().await;
+
+    let ldk_db_path = state.args.ldk_data_dir.join(LDK_DB_FILE);
+
+    let snapshot_path = tmp_path.clone();
+
+    tokio::task::spawn_blocking(move || snapshot(conn, &ldk_db_path, &snapshot_path))
+        .await
+        .context("Backup task panicked")??;
+
+    fs::copy(
+        state.args.ldk_data_dir.join(LDK_SEED_FILE),
+        tmp_path.join(LDK_SEED_FILE),
+    )
+    .context("Failed to copy LDK seed")?;
+
+    let mut files = BTreeMap::new();
+
+    for file in [puncture_core::db::FILE_NAME, LDK_DB_FILE, LDK_SEED_FILE] {
+        files.insert(file.to_string(), hash_file(&tmp_path.join(file))?);
+    }
+
+    let manifest = Manifest {
+        version: BACKUP_VERSION,
+        node_id: state.node_id.to_string(),
+        created_at,
+        files,
+    };
+
+    fs::write(
+        tmp_path.join(MANIFEST_FILE),
+        serde_json::to_string_pretty(&manifest)?,
+    )
+    .context("Failed to write manifest")?;
+
+    for file in [
+        puncture_core::db::FILE_NAME,
+        LDK_DB_FILE,
+        LDK_SEED_FILE,
+        MANIFEST_FILE,
+    ] {
+        fs::set_permissions(tmp_path.join(file), Permissions::from_mode(0o600))
+            .context("Failed to restrict backup permissions")?;
+    }
+
+    let path = backup_dir.join(&name);
+
+    fs::rename(&tmp_path, &path).context("Failed to finalize backup")?;
+
+    info!(?path, "Created backup");
+
+    rotate(&backup_dir, state.args.backup_retention)?;
+
+    Ok(BackupInfo {
+        path: path.display().to_string(),
+        created_at,
+    })
+}
+
+/// Snapshot both databases while holding the lock on our connection. This runs
+/// on a blocking thread since the snapshots may take a while.
+fn snapshot(
+    mut conn: OwnedMutexGuard<SqliteConnection>,
+    ldk_db_path: &Path,
+    path: &Path,
+) -> Result<()> {
+    vacuum_into(&mut conn, &path.join(puncture_core::db::FILE_NAME))?;
+
+    let mut ldk_conn = SqliteConnection::establish(&ldk_db_path.display().to_string())
+        .context("Failed to open LDK database")?;
+
+    sql_query("PRAGMA busy_timeout = 10000").execute(&mut ldk_conn)?;
+
+    vacuum_into(&mut ldk_conn, &path.join(LDK_DB_FILE))
+}
+
+pub fn list(args: &Args) -> Result<ListBackupsResponse> {
+    let mut backups = Vec::new();
+
+    for (created_at, path) in list_backups(&backup_dir(args))? {
+        backups.push(BackupInfo {
+            path: path.display().to_string(),
+            created_at,
+        });
+    }
+
+    Ok(ListBackupsResponse { backups })
+}
+
+/// Validate a backup and restore our database and LDK's seed from it. LDK's
+/// channel state is only restored on explicit request, since channel state
+/// older than the one known to our peers allows them to claim all channel
+/// funds. Without it our peers force close the channels once we reconnect and
+/// the funds return to the on-chain wallet derived from the seed.
+pub fn restore(args: &Args, path: &Path) -> Result<()> {
+    let manifest: Manifest = serde_json::from_str(
+        &fs::read_to_string(path.join(MANIFEST_FILE)).context("Failed to read manifest")?,
+    )
+    .context("Failed to parse manifest")?;
+
+    ensure!(
+        manifest.version == BACKUP_VERSION,
+        "Unsupported backup version {}",
+        manifest.version
+    );
+
+    for (file, hash) in &manifest.files {
+        ensure!(
+            hash_file(&path.join(file))? == *hash,
+            "Checksum mismatch for {file}"
+        );
+    }
+
+    for file in [puncture_core::db::FILE_NAME, LDK_SEED_FILE, LDK_DB_FILE] {
+        ensure!(
+            manifest.files.contains_key(file),
+            "Backup is missing {file}"
+        );
+    }
+
+    check_integrity(&path.join(puncture_core::db::FILE_NAME))?;
+
+    ensure!(
+        secret::exists(&args.puncture_data_dir),
+        "Restore the secret key with puncture-cli secret restore first"
+    );
+
+    let secret_key =
+        secret::read_or_generate(&args.puncture_data_dir, secret_passphrase(args)?.as_deref())?;
+
+    ensure!(
+        secret_key.public().to_string() == manifest.node_id,
+        "Backup was created by node id {}, but our secret key belongs to {}",
+        manifest.node_id,
+        secret_key.public()
+    );
+
+    let seed_path = args.ldk_data_dir.join(LDK_SEED_FILE);
+
+    if seed_path.exists() {
+        ensure!(
+            hash_file(&seed_path)? == manifest.files[LDK_SEED_FILE],
+            "LDK seed differs from the one in the backup, refusing to replace it"
+        );
+    }
+
+    let ldk_db_path = args.ldk_data_dir.join(LDK_DB_FILE);
+
+    if args.i_understand_stale_channel_state {
+        check_integrity(&path.join(LDK_DB_FILE))?;
+
+        ensure!(
+            !ldk_db_path.exists(),
+            "LDK database exists, refusing to replace it with the one in the backup"
+        );
+    }
+
+    let db_path = args.puncture_data_dir.join(puncture_core::db::FILE_NAME);
+
+    if db_path.exists() {
+        let aside = db_path.with_extension(format!("sqlite.pre-restore-{}", unix_time()));
+
+        fs::rename(&db_path, &aside).context("Failed to move current database aside")?;
+
+        warn!(?aside, "Moved current database aside");
+    }
+
+    fs::copy(path.join(puncture_core::db::FILE_NAME), &db_path)
+        .context("Failed to restore database")?;
+
+    fs::create_dir_all(&args.ldk_data_dir).context("Failed to create ldk data directory")?;
+
+    if !seed_path.exists() {
+        fs::copy(path.join(LDK_SEED_FILE), &seed_path).context("Failed to restore LDK seed")?;
+
+        fs::set_permissions(&seed_path, Permissions::from_mode(0o600))
+            .context("Failed to restrict LDK seed permissions")?;
+    }
+
+    if args.i_understand_stale_channel_state {
+        warn!(
+            created_at = manifest.created_at,
+            "RESTORING LDK CHANNEL STATE FROM A BACKUP. IF ANY CHANNEL HAS BEEN UPDATED SINCE THE \
+             BACKUP WAS CREATED, BROADCASTING ITS STALE STATE LETS THE PEER CLAIM ALL ITS FUNDS."
+        );
+
+        fs::copy(path.join(LDK_DB_FILE), &ldk_db_path).context("Failed to restore LDK database")?;
+
+        info!(
+            created_at = manifest.created_at,
+            "Restored puncture and LDK state"
+        );
+
+        return Ok(());
+    }
+
+    if !ldk_db_path.exists() {
+        warn!(
+            "LDK channel state was not restored, your peers will force close the channels and \
+             the funds return to the on-chain wallet"
+        );
+    }
+
+    info!(
+        created_at = manifest.created_at,
+        "Restored puncture database and LDK seed"
+    );
+
+    Ok(())
+}
+
+fn vacuum_into(conn: &mut SqliteConnection, path: &Path) -> Result<()> {
+    let path = path.display().to_string().replace('\'', "''");
+
+    sql_query(format!("VACUUM INTO '{path}'"))
+        .execute(conn)
+        .context("Failed to back up database")?;
+
+    Ok(())
+}
+
+fn check_integrity(path: &Path) -> Result<()> {
+    let mut conn = SqliteConnection::establish(&path.display().to_string())
+        .context("Failed to open database in backup")?;
+
+    let result = sql_query("PRAGMA integrity_check")
+        .load::<IntegrityCheck>(&mut conn)
+        .context("Failed to check database integrity")?;
+
+    ensure!(
+        result.len() == 1 && result[0].integrity_check == "ok",
+        "Database {} is corrupted",
+        path.display()
+    );
+
+    Ok(())
+}
+
+fn hash_file(path: &Path) -> Result<String> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    Ok(sha256::Hash::hash(&bytes).to_string())
+}
+
+/// List the completed backups in the directory, oldest first
+fn list_backups(backup_dir: &Path) -> Result<Vec<(i64, PathBuf)>> {
+    if !backup_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+
+    for entry in fs::read_dir(backup_dir).context("Failed to read backup directory")? {
+        let entry = entry?;
+
+        let created_at = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.strip_prefix(BACKUP_PREFIX))
+            .and_then(|created_at| created_at.parse::<i64>().ok());
+
+        if let Some(created_at) = created_at {
+            backups.push((created_at, entry.path()));
+        }
+    }
+
+    backups.sort();
+
+    Ok(backups)
+}
+
+fn rotate(backup_dir: &Path, retention: usize) -> Result<()> {
+    let backups = list_backups(backup_dir)?;
+
+    for (_, path) in &backups[..backups.len().saturating_sub(retention.max(1))] {
+        info!(?path, "Deleting old backup");
+
+        fs::remove_dir_all(path).context("Failed to delete old backup")?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use axum::body::{Body, to_bytes};
+use axum::extract::{Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use puncture_cli_core::{
+    ROUTE_ADMIN_IMPORT, ROUTE_AUDIT_LIST, ROUTE_BACKUP_CREATE, ROUTE_CONFIG_RELOAD,
+    ROUTE_LDK_CHANNEL_CLOSE, ROUTE_LDK_CHANNEL_CONFIG, ROUTE_LDK_CHANNEL_OPEN,
+    ROUTE_LDK_CHANNEL_ORDER_CREATE, ROUTE_LDK_CHANNEL_ORDER_PAY, ROUTE_LDK_CHANNEL_ORDER_REFRESH,
+    ROUTE_LDK_CHANNEL_REQUEST, ROUTE_LDK_ONCHAIN_DRAIN, ROUTE_LDK_ONCHAIN_RECEIVE,
+    ROUTE_LDK_ONCHAIN_SEND, ROUTE_LDK_PEER_CONNECT, ROUTE_LDK_PEER_DISCONNECT,
+    ROUTE_LDK_SOLVENCY_PUBLISH, ROUTE_SECRET_ROTATE, ROUTE_USER_ADJUST, ROUTE_USER_INVITE_REVOKE,
+    ROUTE_USER_STATUS,
+};
+
+use super::auth::READ_ONLY_ROUTES;
+use crate::AppState;
+
+/// Maximum size of an account archive, which exceeds the usual request limit
+pub const MAX_ARCHIVE_SIZE: usize = 100_000_000;
+
+/// Routes whose request is too large to be stored in the audit log
+const REDACTED_REQUESTS: &[&str] = &[ROUTE_ADMIN_IMPORT];
+
+/// Routes whose successful response is stored in the audit log. Responses of
+/// all other routes are redacted since they may contain bearer credentials
+/// such as invite codes, recovery codes or the secret key mnemonic.
+const AUDITED_RESULTS: &[&str] = &[
+    ROUTE_LDK_SOLVENCY_PUBLISH,
+    ROUTE_LDK_ONCHAIN_RECEIVE,
+    ROUTE_LDK_ONCHAIN_SEND,
+    ROUTE_LDK_ONCHAIN_DRAIN,
+    ROUTE_LDK_CHANNEL_OPEN,
+    ROUTE_LDK_CHANNEL_CLOSE,
+    ROUTE_LDK_CHANNEL_CONFIG,
+    ROUTE_LDK_CHANNEL_REQUEST,
+    ROUTE_LDK_CHANNEL_ORDER_CREATE,
+    ROUTE_LDK_CHANNEL_ORDER_PAY,
+    ROUTE_LDK_CHANNEL_ORDER_REFRESH,
+    ROUTE_LDK_PEER_CONNECT,
+    ROUTE_LDK_PEER_DISCONNECT,
+    ROUTE_USER_INVITE_REVOKE,
+    ROUTE_USER_STATUS,
+    ROUTE_USER_ADJUST,
+    ROUTE_SECRET_ROTATE,
+    ROUTE_ADMIN_IMPORT,
+    ROUTE_BACKUP_CREATE,
+    ROUTE_CONFIG_RELOAD,
+];
+
+/// Identifies who sent a CLI request, inserted by the authentication layer
+#[derive(Clone)]
+pub struct AuditActor(pub String);
+
+/// Record every state changing CLI request with its response in the audit log
+pub async fn record(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let action = request.uri().path().to_string();
+
+    if READ_ONLY_ROUTES.contains(&action.as_str()) || action == ROUTE_AUDIT_LIST {
+        return next.run(request).await;
+    }
+
+    let actor = request
+        .extensions()
+        .get::<AuditActor>()
+        .map(|actor| actor.0.clone())
+        .unwrap_or("unknown".to_string());
+
+    let (parts, body) = request.into_parts();
+
+    let Ok(request) = to_bytes(body, MAX_ARCHIVE_SIZE).await else {
+        return (StatusCode::PAYLOAD_TOO_LARGE, "Request body too large").into_response();
+    };
+
+    let response = next
+        .run(Request::from_parts(parts, Body::from(request.clone())))
+        .await;
+
+    let (parts, body) = response.into_parts();
+
+    let body = to_bytes(body, usize::MAX).await.unwrap_or_default();
+
+    let result = if AUDITED_RESULTS.contains(&action.as_str()) || !parts.status.is_success() {
+        String::from_utf8_lossy(&body).to_string()
+    } else {
+        "redacted".to_string()
+    };
+
+    let audited_request = if REDACTED_REQUESTS.contains(&action.as_str()) {
+        "redacted".to_string()
+    } else {
+        String::from_utf8_lossy(&request).to_string()
+    };
+
+    crate::db::create_audit_entry(
+        &mut *state.db.get_connection().await,
+        actor,
+        action,
+        audited_request,
+        parts.status.as_u16(),
+        Some(result),
+    )
+    .await;
+
+    Response::from_parts(parts, Body::from(body))
+}
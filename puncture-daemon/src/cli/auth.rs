@@ -0,0 +1,128 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use axum::extract::{Request, State};
+use axum::http::header::AUTHORIZATION;
+use axum::middleware::Next;
+use axum::response::Response;
+use bitcoin::hex::DisplayHex;
+use rand::Rng;
+use tracing::warn;
+
+use puncture_cli_core::{
+    ADMIN_TOKEN_FILE, READ_ONLY_TOKEN_FILE, ROUTE_BACKUP_LIST, ROUTE_LDK_BALANCES,
+    ROUTE_LDK_CHANNEL_LIST, ROUTE_LDK_CHANNEL_ORDER_LIST, ROUTE_LDK_FORWARDING_REPORT,
+    ROUTE_LDK_NODE_ID, ROUTE_LDK_PEER_LIST, ROUTE_LDK_SOLVENCY, ROUTE_STATUS, ROUTE_USER_LIST,
+};
+
+use super::CliError;
+use super::audit::AuditActor;
+use crate::AppState;
+
+/// Routes that only read state and can therefore be called with the read-only
+/// token. Invites are excluded, since an invite id lets anyone register.
+pub const READ_ONLY_ROUTES: &[&str] = &[
+    ROUTE_STATUS,
+    ROUTE_BACKUP_LIST,
+    ROUTE_LDK_NODE_ID,
+    ROUTE_LDK_BALANCES,
+    ROUTE_LDK_SOLVENCY,
+    ROUTE_LDK_CHANNEL_LIST,
+    ROUTE_LDK_CHANNEL_ORDER_LIST,
+    ROUTE_LDK_FORWARDING_REPORT,
+    ROUTE_LDK_PEER_LIST,
+    ROUTE_USER_LIST,
+];
+
+/// The bearer tokens accepted by the CLI interface
+#[derive(Clone)]
+pub struct CliTokens {
+    /// Grants access to all routes
+    pub admin: String,
+    /// Grants access to the read-only routes for monitoring
+    pub read_only: String,
+}
+
+impl CliTokens {
+    /// Read the tokens from the data directory or generate them on first startup
+    pub fn read_or_generate(data_dir: &Path) -> Result<Self> {
+        Ok(Self {
+            admin: read_or_generate_token(&data_dir.join(ADMIN_TOKEN_FILE))?,
+            read_only: read_or_generate_token(&data_dir.join(READ_ONLY_TOKEN_FILE))?,
+        })
+    }
+}
+
+/// Read a secret token from file or generate and save a new one
+pub fn read_or_generate_token(file_path: &Path) -> Result<String> {
+    if file_path.exists() {
+        return Ok(fs::read_to_string(file_path)
+            .context("Failed to read token file")?
+            .trim()
+            .to_string());
+    }
+
+    let token = rand::rng().random::<[u8; 32]>().as_hex().to_string();
+
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(file_path)
+        .context("Failed to create token file")?
+        .write_all(token.as_bytes())
+        .context("Failed to write token file")?;
+
+    Ok(token)
+}
+
+pub async fn authenticate(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, CliError> {
+    let token = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.to_string())
+        .ok_or(CliError::unauthorized("Missing bearer token"))?;
+
+    if constant_time_eq(&token, &state.cli_tokens.admin) {
+        request
+            .extensions_mut()
+            .insert(AuditActor("cli:admin".to_string()));
+
+        return Ok(next.run(request).await);
+    }
+
+    if constant_time_eq(&token, &state.cli_tokens.read_only) {
+        if !READ_ONLY_ROUTES.contains(&request.uri().path()) {
+            return Err(CliError::forbidden(
+                "Read-only token cannot access this route",
+            ));
+        }
+
+        return Ok(next.run(request).await);
+    }
+
+    warn!(
+        path = request.uri().path(),
+        "rejected cli request with invalid token"
+    );
+
+    Err(CliError::unauthorized("Invalid bearer token"))
+}
+
+/// Compare secrets without leaking the length of their common prefix through timing
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
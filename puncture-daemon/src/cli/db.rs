@@ -3,9 +3,9 @@ use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl, SqliteConnection};
 
 use puncture_cli_core::UserInfo;
 
-use puncture_core::unix_time;
-use puncture_daemon_db::models::{InviteRecord, RecoveryRecord, User};
-use puncture_daemon_db::schema::{invite, recovery, user};
+use puncture_core::{NodeIdMigration, unix_time};
+use puncture_daemon_db::models::{InviteRecord, MigrationRecord, RecoveryRecord, User};
+use puncture_daemon_db::schema::{invite, migration, recovery, user};
 
 pub async fn create_invite(
     conn: &mut SqliteConnection,
@@ -18,6 +18,7 @@ pub async fn create_invite(
         user_limit: user_limit as i64,
         expires_at: unix_time() + expiry_secs as i64 * 1000,
         created_at: unix_time(),
+        revoked_at: None,
     };
 
     diesel::insert_into(invite::table)
@@ -66,8 +67,25 @@ pub async fn list_users(conn: &mut SqliteConnection) -> Vec<UserInfo> {
             balance_msat: crate::db::user_balance(conn, user_record.user_pk.clone()).await,
             recovery_name: user_record.recovery_name,
             created_at: user_record.created_at,
+            status: user_record
+                .status
+                .parse()
+                .expect("Invalid user status in database"),
+            status_reason: user_record.status_reason,
         });
     }
 
     user_infos
 }
+
+pub async fn create_migration(conn: &mut SqliteConnection, node_id_migration: &NodeIdMigration) {
+    diesel::insert_into(migration::table)
+        .values(&MigrationRecord {
+            old_node_id: node_id_migration.old_node_id.to_string(),
+            new_node_id: node_id_migration.new_node_id.to_string(),
+            signature: node_id_migration.signature.clone(),
+            created_at: node_id_migration.created_at,
+        })
+        .execute(conn)
+        .expect("Failed to create migration");
+}
@@ -1,18 +1,30 @@
+pub mod audit;
+pub mod auth;
 mod db;
+pub mod remote;
 mod rpc;
 
 use std::fmt::Display;
+use std::net::SocketAddr;
 
-use axum::{Router, http::StatusCode, response::IntoResponse, routing::post};
+use axum::{
+    Router, extract::DefaultBodyLimit, http::StatusCode, middleware, response::IntoResponse,
+    routing::post,
+};
 use tokio::net::TcpListener;
 use tokio_util::sync::CancellationToken;
 
 use puncture_cli_core::{
-    ROUTE_LDK_BALANCES, ROUTE_LDK_CHANNEL_CLOSE, ROUTE_LDK_CHANNEL_LIST, ROUTE_LDK_CHANNEL_OPEN,
-    ROUTE_LDK_CHANNEL_REQUEST, ROUTE_LDK_NODE_ID, ROUTE_LDK_ONCHAIN_DRAIN,
-    ROUTE_LDK_ONCHAIN_RECEIVE, ROUTE_LDK_ONCHAIN_SEND, ROUTE_LDK_PEER_CONNECT,
-    ROUTE_LDK_PEER_DISCONNECT, ROUTE_LDK_PEER_LIST, ROUTE_USER_INVITE, ROUTE_USER_LIST,
-    ROUTE_USER_RECOVER,
+    ROUTE_ADMIN_EXPORT, ROUTE_ADMIN_IMPORT, ROUTE_AUDIT_LIST, ROUTE_BACKUP_CREATE,
+    ROUTE_BACKUP_LIST, ROUTE_CONFIG_RELOAD, ROUTE_LDK_BALANCES, ROUTE_LDK_CHANNEL_CLOSE,
+    ROUTE_LDK_CHANNEL_CONFIG, ROUTE_LDK_CHANNEL_LIST, ROUTE_LDK_CHANNEL_OPEN,
+    ROUTE_LDK_CHANNEL_ORDER_CREATE, ROUTE_LDK_CHANNEL_ORDER_LIST, ROUTE_LDK_CHANNEL_ORDER_PAY,
+    ROUTE_LDK_CHANNEL_ORDER_REFRESH, ROUTE_LDK_CHANNEL_REQUEST, ROUTE_LDK_FORWARDING_REPORT,
+    ROUTE_LDK_NODE_ID, ROUTE_LDK_ONCHAIN_DRAIN, ROUTE_LDK_ONCHAIN_RECEIVE, ROUTE_LDK_ONCHAIN_SEND,
+    ROUTE_LDK_PEER_CONNECT, ROUTE_LDK_PEER_DISCONNECT, ROUTE_LDK_PEER_LIST, ROUTE_LDK_SOLVENCY,
+    ROUTE_LDK_SOLVENCY_PUBLISH, ROUTE_SECRET_EXPORT, ROUTE_SECRET_ROTATE, ROUTE_STATUS,
+    ROUTE_USER_ADJUST, ROUTE_USER_INVITE, ROUTE_USER_INVITE_LIST, ROUTE_USER_INVITE_REVOKE,
+    ROUTE_USER_INVITE_SHOW, ROUTE_USER_LIST, ROUTE_USER_RECOVER, ROUTE_USER_STATUS,
 };
 
 use crate::AppState;
@@ -22,10 +34,20 @@ pub async fn run_cli(app_state: AppState, ct: CancellationToken) {
         .await
         .expect("Failed to bind CLI server");
 
-    axum::serve(listener, router().with_state(app_state))
-        .with_graceful_shutdown(ct.cancelled_owned())
-        .await
-        .expect("Failed to start CLI server");
+    let router = router(app_state.clone())
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            auth::authenticate,
+        ))
+        .merge(crate::health::router(app_state));
+
+    axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(ct.cancelled_owned())
+    .await
+    .expect("Failed to start CLI server");
 }
 
 pub struct CliError {
@@ -41,6 +63,20 @@ impl CliError {
         }
     }
 
+    pub fn unauthorized(error: impl Display) -> Self {
+        Self {
+            code: StatusCode::UNAUTHORIZED,
+            error: error.to_string(),
+        }
+    }
+
+    pub fn forbidden(error: impl Display) -> Self {
+        Self {
+            code: StatusCode::FORBIDDEN,
+            error: error.to_string(),
+        }
+    }
+
     pub fn internal(error: impl Display) -> Self {
         Self {
             code: StatusCode::INTERNAL_SERVER_ERROR,
@@ -55,21 +91,66 @@ impl IntoResponse for CliError {
     }
 }
 
-pub fn router() -> Router<AppState> {
+pub fn router(app_state: AppState) -> Router {
     Router::new()
+        .route(ROUTE_STATUS, post(rpc::status))
         .route(ROUTE_LDK_NODE_ID, post(rpc::ldk_node_id))
         .route(ROUTE_LDK_BALANCES, post(rpc::ldk_balances))
+        .route(ROUTE_LDK_SOLVENCY, post(rpc::ldk_solvency))
+        .route(ROUTE_LDK_SOLVENCY_PUBLISH, post(rpc::ldk_solvency_publish))
         .route(ROUTE_LDK_ONCHAIN_RECEIVE, post(rpc::ldk_onchain_receive))
         .route(ROUTE_LDK_ONCHAIN_SEND, post(rpc::ldk_onchain_send))
         .route(ROUTE_LDK_ONCHAIN_DRAIN, post(rpc::ldk_onchain_drain))
         .route(ROUTE_LDK_CHANNEL_OPEN, post(rpc::ldk_channel_open))
         .route(ROUTE_LDK_CHANNEL_CLOSE, post(rpc::ldk_channel_close))
         .route(ROUTE_LDK_CHANNEL_LIST, post(rpc::ldk_channel_list))
+        .route(ROUTE_LDK_CHANNEL_CONFIG, post(rpc::ldk_channel_config))
+        .route(
+            ROUTE_LDK_FORWARDING_REPORT,
+            post(rpc::ldk_forwarding_report),
+        )
         .route(ROUTE_LDK_CHANNEL_REQUEST, post(rpc::ldk_channel_request))
+        .route(
+            ROUTE_LDK_CHANNEL_ORDER_CREATE,
+            post(rpc::ldk_channel_order_create),
+        )
+        .route(
+            ROUTE_LDK_CHANNEL_ORDER_PAY,
+            post(rpc::ldk_channel_order_pay),
+        )
+        .route(
+            ROUTE_LDK_CHANNEL_ORDER_REFRESH,
+            post(rpc::ldk_channel_order_refresh),
+        )
+        .route(
+            ROUTE_LDK_CHANNEL_ORDER_LIST,
+            post(rpc::ldk_channel_order_list),
+        )
         .route(ROUTE_LDK_PEER_CONNECT, post(rpc::ldk_peer_connect))
         .route(ROUTE_LDK_PEER_DISCONNECT, post(rpc::ldk_peer_disconnect))
         .route(ROUTE_LDK_PEER_LIST, post(rpc::ldk_peer_list))
         .route(ROUTE_USER_INVITE, post(rpc::user_invite))
+        .route(ROUTE_USER_INVITE_LIST, post(rpc::user_invite_list))
+        .route(ROUTE_USER_INVITE_SHOW, post(rpc::user_invite_show))
+        .route(ROUTE_USER_INVITE_REVOKE, post(rpc::user_invite_revoke))
         .route(ROUTE_USER_RECOVER, post(rpc::user_recover))
         .route(ROUTE_USER_LIST, post(rpc::user_list))
+        .route(ROUTE_USER_STATUS, post(rpc::user_status))
+        .route(ROUTE_USER_ADJUST, post(rpc::user_adjust))
+        .route(ROUTE_SECRET_EXPORT, post(rpc::secret_export))
+        .route(ROUTE_SECRET_ROTATE, post(rpc::secret_rotate))
+        .route(ROUTE_AUDIT_LIST, post(rpc::audit_list))
+        .route(ROUTE_ADMIN_EXPORT, post(rpc::admin_export))
+        .route(ROUTE_BACKUP_CREATE, post(rpc::backup_create))
+        .route(ROUTE_BACKUP_LIST, post(rpc::backup_list))
+        .route(ROUTE_CONFIG_RELOAD, post(rpc::config_reload))
+        .route(
+            ROUTE_ADMIN_IMPORT,
+            post(rpc::admin_import).layer(DefaultBodyLimit::max(audit::MAX_ARCHIVE_SIZE)),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            audit::record,
+        ))
+        .with_state(app_state)
 }
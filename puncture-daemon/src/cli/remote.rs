@@ -0,0 +1,72 @@
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, header::CONTENT_TYPE};
+use iroh::endpoint::Connection;
+use tower::ServiceExt;
+use tracing::{info, warn};
+
+use puncture_cli_core::{AdminRpcRequest, AdminRpcResponse};
+
+use super::audit::{AuditActor, MAX_ARCHIVE_SIZE};
+use crate::AppState;
+
+/// Requests have to fit an account archive for a remote admin import, plus
+/// the envelope naming the route
+const MAX_REQUEST_SIZE: usize = MAX_ARCHIVE_SIZE + 100_000;
+
+/// Serve the CLI routes to a remote puncture-cli connected via the admin ALPN.
+/// Authorization is given by the remote node id being configured as an admin.
+pub async fn handle_connection(app_state: AppState, connection: Connection) {
+    if let Err(e) = handle_connection_inner(app_state, connection).await {
+        warn!(?e, "Error handling admin connection");
+    }
+}
+
+async fn handle_connection_inner(
+    app_state: AppState,
+    connection: Connection,
+) -> anyhow::Result<()> {
+    let node_id = connection.remote_node_id()?;
+
+    if !app_state.args.admin_node_ids.contains(&node_id) {
+        warn!(%node_id, "rejected admin connection from unknown node id");
+
+        connection.close(0u32.into(), b"Unknown admin node id");
+
+        return Ok(());
+    }
+
+    info!(%node_id, "accepted admin connection");
+
+    while let Ok((mut send_stream, mut recv_stream)) = connection.accept_bi().await {
+        let request = recv_stream.read_to_end(MAX_REQUEST_SIZE).await?;
+
+        let request: AdminRpcRequest = serde_json::from_slice(&request)?;
+
+        info!(%node_id, route = request.route, "handling admin request");
+
+        let mut http_request = Request::post(request.route)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(serde_json::to_vec(&request.request)?))?;
+
+        http_request
+            .extensions_mut()
+            .insert(AuditActor(format!("node:{node_id}")));
+
+        let response = super::router(app_state.clone())
+            .oneshot(http_request)
+            .await?;
+
+        let response = AdminRpcResponse {
+            status: response.status().as_u16(),
+            body: String::from_utf8(to_bytes(response.into_body(), usize::MAX).await?.to_vec())?,
+        };
+
+        let response = serde_json::to_vec(&response).expect("Failed to serialize response");
+
+        send_stream.write_all(&response).await?;
+
+        send_stream.finish()?;
+    }
+
+    Ok(())
+}
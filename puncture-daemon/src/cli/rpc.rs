@@ -1,6 +1,8 @@
+use std::net::SocketAddr;
 use std::str::FromStr;
 
-use axum::extract::{Json, State};
+use axum::Extension;
+use axum::extract::{ConnectInfo, Json, State};
 use bitcoin::hex::{DisplayHex, FromHex};
 use bitcoin::{FeeRate, Txid};
 use ldk_node::UserChannelId;
@@ -10,18 +12,30 @@ use serde_json::Value;
 use tracing::info;
 
 use puncture_cli_core::{
-    BalancesResponse, ChannelInfo, CloseChannelRequest, ConnectPeerRequest, DisconnectPeerRequest,
-    InviteRequest, InviteResponse, ListChannelsResponse, ListPeersResponse, ListUsersResponse,
+    AdjustBalanceRequest, AdjustBalanceResponse, AuditListRequest, AuditListResponse, BackupInfo,
+    BalancesResponse, ChannelConfigInfo, ChannelInfo, ChannelOrderIdRequest, ChannelOrderInfo,
+    CloseChannelRequest, ConnectPeerRequest, CreateChannelOrderRequest, DisconnectPeerRequest,
+    ForwardingReportRequest, ForwardingReportResponse, ImportResponse, InviteIdRequest, InviteInfo,
+    InviteRequest, InviteResponse, ListBackupsResponse, ListChannelOrdersResponse,
+    ListChannelsResponse, ListInvitesResponse, ListPeersResponse, ListUsersResponse,
     NodeIdResponse, OnchainDrainRequest, OnchainReceiveResponse, OnchainSendRequest,
-    OpenChannelRequest, OpenChannelResponse, PeerInfo, RecoverRequest, RecoverResponse,
-    RequestChannelRequest, RequestChannelResponse,
+    OpenChannelRequest, OpenChannelResponse, PayChannelOrderRequest, PeerInfo, RecoverRequest,
+    RecoverResponse, RequestChannelRequest, RequestChannelResponse, SecretExportResponse,
+    SecretRotateResponse, SetUserStatusRequest, Settings, ShowInviteResponse, SignedArchive,
+    SolvencyResponse, StatusResponse, UpdateChannelConfigRequest, UserInfo, UserStatus,
 };
-use puncture_core::PunctureCode;
+use puncture_core::{NodeIdMigration, PunctureCode, secret};
 
-use crate::AppState;
+use crate::convert::IntoPayment;
+use crate::{AppState, secret_passphrase};
 
 use super::{CliError, db};
 
+#[axum::debug_handler]
+pub async fn status(State(state): State<AppState>) -> Json<StatusResponse> {
+    Json(crate::health::status(&state).await)
+}
+
 #[axum::debug_handler]
 pub async fn ldk_node_id(State(state): State<AppState>) -> Json<NodeIdResponse> {
     Json(NodeIdResponse {
@@ -58,6 +72,21 @@ pub async fn ldk_balances(
     }))
 }
 
+#[axum::debug_handler]
+pub async fn ldk_solvency(State(state): State<AppState>) -> Json<SolvencyResponse> {
+    Json(crate::solvency::report(&state).await)
+}
+
+#[axum::debug_handler]
+pub async fn ldk_solvency_publish(
+    State(state): State<AppState>,
+) -> Result<Json<SolvencyResponse>, CliError> {
+    crate::solvency::publish(&state)
+        .await
+        .map(Json)
+        .map_err(CliError::internal)
+}
+
 #[axum::debug_handler]
 pub async fn ldk_onchain_receive(
     State(state): State<AppState>,
@@ -208,85 +237,100 @@ pub async fn ldk_channel_list(
             funding_txo: channel.funding_txo,
             confirmations: channel.confirmations,
             confirmations_required: channel.confirmations_required,
+            config: crate::forwarding::channel_config_info(&channel.config),
         })
         .collect();
 
     Ok(Json(ListChannelsResponse { channels }))
 }
 
+#[axum::debug_handler]
+pub async fn ldk_channel_config(
+    State(state): State<AppState>,
+    Json(request): Json<UpdateChannelConfigRequest>,
+) -> Result<Json<ChannelConfigInfo>, CliError> {
+    crate::forwarding::update_channel_config(&state, &request)
+        .map(Json)
+        .map_err(CliError::bad_request)
+}
+
+#[axum::debug_handler]
+pub async fn ldk_forwarding_report(
+    State(state): State<AppState>,
+    Json(request): Json<ForwardingReportRequest>,
+) -> Json<ForwardingReportResponse> {
+    Json(crate::forwarding::report(&state, request.since_days).await)
+}
+
 #[axum::debug_handler]
 pub async fn ldk_channel_request(
     State(state): State<AppState>,
     Json(request): Json<RequestChannelRequest>,
 ) -> Result<Json<RequestChannelResponse>, CliError> {
-    // Connect to Megalith LSP
-    state
-        .node
-        .connect(
-            "038a9e56512ec98da2b5789761f7af8f280baf98a09282360cd6ff1381b5e889bf"
-                .parse()
-                .unwrap(),
-            "64.23.162.51:9735".parse().unwrap(),
-            true,
-        )
-        .map_err(|_| CliError::internal("Failed to connect to Megalith LSP node"))?;
-
-    info!("Ensured connection to Megalith LSP node");
-
-    let client = reqwest::Client::new();
-
-    // Create request payload for Megalith LSPS1 API
-    let payload = serde_json::json!({
-        "lsp_balance_sat": request.lsp_balance_sat.to_string(),
-        "client_balance_sat": request.client_balance_sat.to_string(),
-        "required_channel_confirmations": 0,
-        "funding_confirms_within_blocks": 6,
-        "channel_expiry_blocks": request.channel_expiry_blocks,
-        "token": "",
-        "refund_on_chain_address": null,
-        "announce_channel": request.public,
-        "public_key": state.node.node_id().to_string()
-    });
-
-    // Make HTTP request to Megalith LSPS1 API
-    let response = client
-        .post("https://megalithic.me/api/lsps1/v1/create_order")
-        .header("Content-Type", "application/json")
-        .json(&payload)
-        .send()
-        .await
-        .map_err(|e| CliError::internal(format!("Failed to call Megalith API: {e}")))?;
-
-    if !response.status().is_success() {
-        return Err(CliError::bad_request(format!(
-            "Megalith API error: {}",
-            response
-                .text()
-                .await
-                .unwrap_or_else(|_| "Unknown error".to_string())
-        )));
-    }
-
-    // Parse response to get the BOLT11 invoice
-    let api_response: Value = response
-        .json()
-        .await
-        .map_err(|e| CliError::internal(format!("Failed to parse Megalith response: {e}")))?;
+    let provider = crate::lsp::lsps1_provider(&state.args, request.provider.as_deref())
+        .map_err(CliError::bad_request)?;
 
-    let invoice = api_response
-        .get("payment")
-        .and_then(|v| v.get("bolt11"))
-        .and_then(|v| v.get("invoice"))
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| CliError::internal("Missing invoice in Megalith response"))?;
+    let invoice = crate::lsp::request_channel(
+        &state,
+        &provider,
+        request.lsp_balance_sat,
+        request.client_balance_sat,
+        request.channel_expiry_blocks,
+        request.public,
+    )
+    .await
+    .map_err(CliError::bad_request)?;
 
-    info!(?request, ?invoice, "requested channel from Megalith LSP");
+    info!(?request, ?invoice, "requested channel from LSP");
 
     Ok(Json(RequestChannelResponse {
-        invoice: invoice.to_string(),
+        provider: provider.name,
+        invoice,
     }))
 }
 
+#[axum::debug_handler]
+pub async fn ldk_channel_order_create(
+    State(state): State<AppState>,
+    Json(request): Json<CreateChannelOrderRequest>,
+) -> Result<Json<ChannelOrderInfo>, CliError> {
+    crate::lsp::create_order(&state, &request)
+        .await
+        .map(Json)
+        .map_err(CliError::bad_request)
+}
+
+#[axum::debug_handler]
+pub async fn ldk_channel_order_pay(
+    State(state): State<AppState>,
+    Json(request): Json<PayChannelOrderRequest>,
+) -> Result<Json<ChannelOrderInfo>, CliError> {
+    crate::lsp::pay_order(&state, &request.order_id, request.method)
+        .await
+        .map(Json)
+        .map_err(CliError::bad_request)
+}
+
+#[axum::debug_handler]
+pub async fn ldk_channel_order_refresh(
+    State(state): State<AppState>,
+    Json(request): Json<ChannelOrderIdRequest>,
+) -> Result<Json<ChannelOrderInfo>, CliError> {
+    crate::lsp::refresh_order(&state, &request.order_id)
+        .await
+        .map(Json)
+        .map_err(CliError::bad_request)
+}
+
+#[axum::debug_handler]
+pub async fn ldk_channel_order_list(
+    State(state): State<AppState>,
+) -> Json<ListChannelOrdersResponse> {
+    Json(ListChannelOrdersResponse {
+        orders: crate::db::list_channel_orders(&mut *state.db.get_connection().await).await,
+    })
+}
+
 #[axum::debug_handler]
 pub async fn ldk_peer_connect(
     State(state): State<AppState>,
@@ -363,6 +407,68 @@ pub async fn user_invite(
     }))
 }
 
+/// Accept both the invite id and the invite code it was shared as
+fn parse_invite_id(invite_id: &str) -> Result<String, CliError> {
+    if !invite_id.starts_with("pct") {
+        return Ok(invite_id.to_string());
+    }
+
+    PunctureCode::decode(invite_id)
+        .map_err(CliError::bad_request)?
+        .to_invite()
+        .map(|invite| invite.id())
+        .map_err(CliError::bad_request)
+}
+
+#[axum::debug_handler]
+pub async fn user_invite_list(State(state): State<AppState>) -> Json<ListInvitesResponse> {
+    Json(ListInvitesResponse {
+        invites: crate::db::list_invites(&mut *state.db.get_connection().await).await,
+    })
+}
+
+#[axum::debug_handler]
+pub async fn user_invite_show(
+    State(state): State<AppState>,
+    Json(request): Json<InviteIdRequest>,
+) -> Result<Json<ShowInviteResponse>, CliError> {
+    let invite_id = parse_invite_id(&request.invite_id)?;
+
+    let mut conn = state.db.get_connection().await;
+
+    let info = crate::db::get_invite(&mut conn, &invite_id)
+        .await
+        .ok_or(CliError::bad_request("Unknown invite"))?;
+
+    let id = <[u8; 16]>::from_hex(&info.id).map_err(CliError::internal)?;
+
+    Ok(Json(ShowInviteResponse {
+        invite: PunctureCode::invite(id, state.node_id).encode(),
+        users: crate::db::list_invite_users(&mut conn, &invite_id).await,
+        info,
+    }))
+}
+
+#[axum::debug_handler]
+pub async fn user_invite_revoke(
+    State(state): State<AppState>,
+    Json(request): Json<InviteIdRequest>,
+) -> Result<Json<InviteInfo>, CliError> {
+    let invite_id = parse_invite_id(&request.invite_id)?;
+
+    let mut conn = state.db.get_connection().await;
+
+    if !crate::db::revoke_invite(&mut conn, &invite_id).await {
+        return Err(CliError::bad_request("Unknown or already revoked invite"));
+    }
+
+    Ok(Json(
+        crate::db::get_invite(&mut conn, &invite_id)
+            .await
+            .expect("Revoked invite exists"),
+    ))
+}
+
 #[tracing::instrument(skip(state))]
 pub async fn user_recover(
     State(state): State<AppState>,
@@ -383,8 +489,170 @@ pub async fn user_recover(
     }))
 }
 
+#[axum::debug_handler]
+pub async fn user_status(
+    State(state): State<AppState>,
+    Json(request): Json<SetUserStatusRequest>,
+) -> Result<Json<UserInfo>, CliError> {
+    let mut conn = state.db.get_connection().await;
+
+    let user = crate::db::get_user_info(&mut conn, request.user_pk.clone())
+        .await
+        .ok_or(CliError::bad_request("User does not exist"))?;
+
+    if user.status == UserStatus::Deleted {
+        return Err(CliError::bad_request("Deleted users cannot be changed"));
+    }
+
+    crate::db::set_user_status(
+        &mut conn,
+        request.user_pk.clone(),
+        request.status,
+        request.reason.clone(),
+    )
+    .await;
+
+    state.event_bus.send_status_event(request.user_pk);
+
+    Ok(Json(UserInfo {
+        status: request.status,
+        status_reason: request.reason,
+        ..user
+    }))
+}
+
+#[axum::debug_handler]
+pub async fn user_adjust(
+    State(state): State<AppState>,
+    Json(request): Json<AdjustBalanceRequest>,
+) -> Result<Json<AdjustBalanceResponse>, CliError> {
+    let (record, balance_msat) = crate::db::adjust_balance(
+        &mut *state.db.get_connection().await,
+        request.user_pk.clone(),
+        request.amount_msat,
+        request.memo,
+    )
+    .await
+    .map_err(CliError::bad_request)?;
+
+    state
+        .event_bus
+        .send_balance_event(request.user_pk.clone(), balance_msat);
+
+    state
+        .event_bus
+        .send_payment_event(request.user_pk, record.clone().into_payment(true));
+
+    Ok(Json(AdjustBalanceResponse {
+        id: record.id,
+        balance_msat,
+    }))
+}
+
 pub async fn user_list(State(state): State<AppState>) -> Result<Json<ListUsersResponse>, CliError> {
     Ok(Json(ListUsersResponse {
         users: db::list_users(&mut *state.db.get_connection().await).await,
     }))
 }
+
+/// The mnemonic is only served to callers on this machine, requests forwarded
+/// from remote admins carry no socket address and are refused as well
+#[axum::debug_handler]
+pub async fn secret_export(
+    State(state): State<AppState>,
+    connect_info: Option<Extension<ConnectInfo<SocketAddr>>>,
+) -> Result<Json<SecretExportResponse>, CliError> {
+    if !connect_info.is_some_and(|Extension(ConnectInfo(addr))| addr.ip().is_loopback()) {
+        return Err(CliError::forbidden(
+            "The secret key can only be exported from localhost",
+        ));
+    }
+
+    let passphrase = secret_passphrase(&state.args).map_err(CliError::internal)?;
+
+    // Read the key from disk since a rotation only takes effect after a restart
+    let secret_key = secret::read(&state.args.puncture_data_dir, passphrase.as_deref())
+        .map_err(CliError::internal)?;
+
+    info!("exported secret key mnemonic");
+
+    Ok(Json(SecretExportResponse {
+        mnemonic: secret::to_mnemonic(&secret_key),
+    }))
+}
+
+#[axum::debug_handler]
+pub async fn secret_rotate(
+    State(state): State<AppState>,
+) -> Result<Json<SecretRotateResponse>, CliError> {
+    let passphrase = secret_passphrase(&state.args).map_err(CliError::internal)?;
+
+    let (old, new) = secret::rotate(&state.args.puncture_data_dir, passphrase.as_deref())
+        .map_err(CliError::internal)?;
+
+    let migration = NodeIdMigration::new(&old, new.public());
+
+    db::create_migration(&mut *state.db.get_connection().await, &migration).await;
+
+    info!(?migration, "rotated secret key");
+
+    Ok(Json(SecretRotateResponse {
+        old_node_id: old.public().to_string(),
+        new_node_id: new.public().to_string(),
+    }))
+}
+
+#[axum::debug_handler]
+pub async fn admin_export(State(state): State<AppState>) -> Result<Json<SignedArchive>, CliError> {
+    crate::archive::export(&state)
+        .await
+        .map(Json)
+        .map_err(CliError::bad_request)
+}
+
+#[axum::debug_handler]
+pub async fn admin_import(
+    State(state): State<AppState>,
+    Json(request): Json<SignedArchive>,
+) -> Result<Json<ImportResponse>, CliError> {
+    crate::archive::import(&state, request)
+        .await
+        .map(Json)
+        .map_err(CliError::bad_request)
+}
+
+#[axum::debug_handler]
+pub async fn backup_create(State(state): State<AppState>) -> Result<Json<BackupInfo>, CliError> {
+    crate::backup::create(&state)
+        .await
+        .map(Json)
+        .map_err(CliError::internal)
+}
+
+#[axum::debug_handler]
+pub async fn backup_list(
+    State(state): State<AppState>,
+) -> Result<Json<ListBackupsResponse>, CliError> {
+    crate::backup::list(&state.args)
+        .map(Json)
+        .map_err(CliError::internal)
+}
+
+#[axum::debug_handler]
+pub async fn audit_list(
+    State(state): State<AppState>,
+    Json(request): Json<AuditListRequest>,
+) -> Json<AuditListResponse> {
+    Json(AuditListResponse {
+        entries: crate::db::list_audit_entries(&mut *state.db.get_connection().await, request)
+            .await,
+    })
+}
+
+#[axum::debug_handler]
+pub async fn config_reload(State(state): State<AppState>) -> Result<Json<Settings>, CliError> {
+    crate::config::reload(&state)
+        .await
+        .map(Json)
+        .map_err(CliError::bad_request)
+}
@@ -6,20 +6,24 @@ use lightning_invoice::Bolt11Invoice;
 use rand::Rng;
 use tracing::info;
 
+use puncture_cli_core::UserStatus;
 use puncture_core::unix_time;
 use puncture_daemon_db::models::{
-    InviteRecord, InvoiceRecord, OfferRecord, ReceiveRecord, RecoveryRecord, SendRecord, User,
+    AdjustmentRecord, InviteRecord, InvoiceRecord, OfferRecord, ReceiveRecord, RecoveryCodeRecord,
+    RecoveryRecord, SendRecord, User,
+};
+use puncture_daemon_db::schema::{
+    adjustment, invite, invoice, offer, receive, recovery, recovery_code, send, user,
 };
-use puncture_daemon_db::schema::{invite, invoice, offer, receive, recovery, send, user};
 
 use crate::convert::IntoPayment;
 
-pub async fn user_exists(conn: &mut diesel::SqliteConnection, user_pk: String) -> bool {
-    diesel::select(diesel::dsl::exists(
-        user::table.filter(user::user_pk.eq(user_pk)),
-    ))
-    .get_result::<bool>(conn)
-    .expect("Failed to check if user exists")
+pub async fn get_user(conn: &mut diesel::SqliteConnection, user_pk: String) -> Option<User> {
+    user::table
+        .filter(user::user_pk.eq(user_pk))
+        .first::<User>(conn)
+        .optional()
+        .expect("Failed to query user")
 }
 
 pub async fn get_invite(
@@ -56,6 +60,8 @@ pub async fn register_user_with_invite(
             invite_id,
             created_at: unix_time(),
             recovery_name: None,
+            status: UserStatus::Active.to_string(),
+            status_reason: None,
         })
         .on_conflict(user::user_pk)
         .do_nothing()
@@ -76,6 +82,61 @@ pub async fn get_recovery(
         .expect("Failed to query recovery")
 }
 
+pub async fn create_recovery_code(
+    conn: &mut diesel::SqliteConnection,
+    user_pk: String,
+    id_hash: String,
+    pin_hash: Option<String>,
+    expires_at: i64,
+) {
+    let new_recovery_code = RecoveryCodeRecord {
+        id_hash,
+        user_pk: user_pk.clone(),
+        pin_hash,
+        failed_attempts: 0,
+        created_at: unix_time(),
+        expires_at,
+        last_failed_at: None,
+    };
+
+    conn.transaction(|conn| {
+        diesel::delete(recovery_code::table.filter(recovery_code::user_pk.eq(user_pk)))
+            .execute(conn)?;
+
+        diesel::insert_into(recovery_code::table)
+            .values(&new_recovery_code)
+            .execute(conn)?;
+
+        Ok::<(), diesel::result::Error>(())
+    })
+    .expect("Failed to create recovery code");
+}
+
+pub async fn get_recovery_code(
+    conn: &mut diesel::SqliteConnection,
+    id_hash: &str,
+) -> Option<RecoveryCodeRecord> {
+    let id_hash = id_hash.to_string();
+
+    recovery_code::table
+        .filter(recovery_code::id_hash.eq(id_hash))
+        .first::<RecoveryCodeRecord>(conn)
+        .optional()
+        .expect("Failed to query recovery code")
+}
+
+pub async fn increment_recovery_code_attempts(conn: &mut diesel::SqliteConnection, id_hash: &str) {
+    let id_hash = id_hash.to_string();
+
+    diesel::update(recovery_code::table.filter(recovery_code::id_hash.eq(id_hash)))
+        .set((
+            recovery_code::failed_attempts.eq(recovery_code::failed_attempts + 1),
+            recovery_code::last_failed_at.eq(unix_time()),
+        ))
+        .execute(conn)
+        .expect("Failed to increment recovery code attempts");
+}
+
 pub async fn create_invoice(
     conn: &mut diesel::SqliteConnection,
     user_pk: String,
@@ -169,6 +230,69 @@ pub async fn create_internal_transfer(
     pr: String,
     description: String,
 ) -> (SendRecord, ReceiveRecord) {
+    conn.transaction(|conn| {
+        insert_internal_transfer(
+            conn,
+            send_user_pk,
+            receive_user_pk,
+            amount_msat,
+            fee_msat,
+            pr,
+            description,
+        )
+    })
+    .expect("Failed to create internal transfer")
+}
+
+/// Sweep the entire balance of the recovered user and consume the recovery in
+/// the same transaction, such that a recovery can only ever be used once. The
+/// recovery is identified by the id of an operator created recovery or by the
+/// id hash of a user created recovery code.
+pub async fn recover_balance(
+    conn: &mut diesel::SqliteConnection,
+    recovery_user_pk: String,
+    user_pk: String,
+    recovery_pr: String,
+) -> Result<(SendRecord, ReceiveRecord), String> {
+    conn.immediate_transaction(|conn| {
+        let balance_msat = crate::db::balance_msat(conn, &recovery_user_pk)?;
+
+        if balance_msat <= 0 {
+            return Ok(Err("User has no balance to recover".to_string()));
+        }
+
+        let consumed = diesel::delete(recovery::table.filter(recovery::id.eq(&recovery_pr)))
+            .execute(conn)?
+            + diesel::delete(recovery_code::table.filter(recovery_code::id_hash.eq(&recovery_pr)))
+                .execute(conn)?;
+
+        if consumed == 0 {
+            return Ok(Err("Recovery has already been used".to_string()));
+        }
+
+        insert_internal_transfer(
+            conn,
+            recovery_user_pk,
+            user_pk,
+            balance_msat,
+            0,
+            recovery_pr,
+            "Recovery".to_string(),
+        )
+        .map(Ok)
+    })
+    .expect("Failed to recover balance")
+}
+
+fn insert_internal_transfer(
+    conn: &mut diesel::SqliteConnection,
+    send_user_pk: String,
+    receive_user_pk: String,
+    amount_msat: i64,
+    fee_msat: i64,
+    pr: String,
+    description: String,
+) -> diesel::QueryResult<(SendRecord, ReceiveRecord)> {
     let transfer_id = rand::rng().random::<[u8; 32]>().as_hex().to_string();
 
     info!(
@@ -200,20 +324,15 @@ pub async fn create_internal_transfer(
         created_at: unix_time(),
     };
 
-    conn.transaction(|conn| {
-        diesel::insert_into(send::table)
-            .values(&send_record)
-            .execute(conn)?;
-
-        diesel::insert_into(receive::table)
-            .values(&receive_record)
-            .execute(conn)?;
+    diesel::insert_into(send::table)
+        .values(&send_record)
+        .execute(conn)?;
 
-        Ok::<(), diesel::result::Error>(())
-    })
-    .expect("Failed to create internal transfer");
+    diesel::insert_into(receive::table)
+        .values(&receive_record)
+        .execute(conn)?;
 
-    (send_record, receive_record)
+    Ok((send_record, receive_record))
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -267,7 +386,7 @@ pub async fn user_payments(
 
     // Load full SendRecord records and convert using IntoPayment trait
     let send_payments: Vec<puncture_client_core::Payment> = send::table
-        .filter(send::user_pk.eq(user_pk))
+        .filter(send::user_pk.eq(user_pk.clone()))
         .order(send::created_at.desc())
         .limit(50)
         .load::<SendRecord>(conn)
@@ -276,7 +395,17 @@ pub async fn user_payments(
         .map(|record| record.into_payment(false))
         .collect();
 
-    let mut payments = [receive_payments, send_payments].concat();
+    let adjustment_payments: Vec<puncture_client_core::Payment> = adjustment::table
+        .filter(adjustment::user_pk.eq(user_pk))
+        .order(adjustment::created_at.desc())
+        .limit(50)
+        .load::<AdjustmentRecord>(conn)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|record| record.into_payment(false))
+        .collect();
+
+    let mut payments = [receive_payments, send_payments, adjustment_payments].concat();
 
     payments.sort_by_key(|payment| payment.created_at);
 
@@ -2,6 +2,7 @@ mod db;
 mod rpc;
 
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
 use std::{future, sync::Arc};
 
 use anyhow::{Result, anyhow, ensure};
@@ -14,11 +15,14 @@ use tokio_stream::{Stream, StreamExt};
 use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
 
+use puncture_cli_core::ADMIN_ALPN;
 use puncture_client_core::{
     AppEvent, Balance, ClientRpcRequest, ENDPOINT_BOLT11_RECEIVE, ENDPOINT_BOLT11_SEND,
-    ENDPOINT_BOLT12_RECEIVE, ENDPOINT_BOLT12_SEND, ENDPOINT_ONCHAIN_SEND, ENDPOINT_RECOVER,
-    ENDPOINT_REGISTER, ENDPOINT_SET_RECOVERY_NAME,
+    ENDPOINT_BOLT12_RECEIVE, ENDPOINT_BOLT12_SEND, ENDPOINT_CREATE_RECOVERY_CODE,
+    ENDPOINT_LIABILITY_PROOF, ENDPOINT_MIGRATION, ENDPOINT_ONCHAIN_SEND, ENDPOINT_RECEIVABLE_MAX,
+    ENDPOINT_RECOVER, ENDPOINT_REGISTER, ENDPOINT_SET_RECOVERY_NAME,
 };
+use puncture_core::NodeIdMigration;
 
 use crate::AppState;
 
@@ -27,8 +31,12 @@ macro_rules! client_method {
         async move {
             let mut conn = $state.db.get_connection().await;
 
-            if $auth && !db::user_exists(&mut conn, $user_id.clone()).await {
-                return Err("Method requires a registered user".to_string());
+            if $auth {
+                let Some(user) = db::get_user(&mut conn, $user_id.clone()).await else {
+                    return Err("Method requires a registered user".to_string());
+                };
+
+                rpc::check_not_blocked(&user)?;
             }
 
             drop(conn);
@@ -49,9 +57,9 @@ pub async fn run_api(endpoint: Endpoint, app_state: AppState, ct: CancellationTo
         endpoint.node_id()
     );
 
-    let app_state = Arc::new(app_state);
+    let semaphore = app_state.metrics.connections.clone();
 
-    let semaphore = Arc::new(DashMap::new());
+    let app_state = Arc::new(app_state);
 
     loop {
         tokio::select! {
@@ -67,6 +75,60 @@ pub async fn run_api(endpoint: Endpoint, app_state: AppState, ct: CancellationTo
     endpoint.close().await;
 }
 
+/// Serve the migration statement of a retired secret key to clients that
+/// still connect to its node id
+pub async fn run_migration(endpoint: Endpoint, migration: NodeIdMigration, ct: CancellationToken) {
+    info!(?migration, "Serving node id migration");
+
+    loop {
+        tokio::select! {
+            incoming = endpoint.accept() => {
+                tokio::spawn(handle_migration_connection(migration.clone(), incoming.unwrap()));
+            }
+            _ = ct.cancelled() => {
+                break;
+            }
+        }
+    }
+
+    endpoint.close().await;
+}
+
+async fn handle_migration_connection(migration: NodeIdMigration, incoming: Incoming) {
+    if let Err(e) = handle_migration_connection_inner(migration, incoming).await {
+        warn!(?e, "Error handling migration connection");
+    }
+}
+
+async fn handle_migration_connection_inner(
+    migration: NodeIdMigration,
+    incoming: Incoming,
+) -> anyhow::Result<()> {
+    let connection = incoming.accept()?.await?;
+
+    while let Ok((mut send_stream, mut recv_stream)) = connection.accept_bi().await {
+        let request = recv_stream.read_to_end(100_000).await?;
+
+        let request: ClientRpcRequest<Value> = serde_json::from_slice(&request)?;
+
+        let response = match request.method.as_str() {
+            ENDPOINT_MIGRATION => Ok(serde_json::to_value(&migration).unwrap()),
+            _ => Err(format!(
+                "Daemon has moved to node id {}",
+                migration.new_node_id
+            )),
+        };
+
+        let response = serde_json::to_vec(&response).expect("Failed to serialize response");
+
+        send_stream.write_all(&response).await?;
+
+        send_stream.finish()?;
+    }
+
+    Ok(())
+}
+
 async fn handle_connection(
     app_state: Arc<AppState>,
     semaphore: Arc<DashMap<String, AtomicUsize>>,
@@ -86,6 +148,12 @@ async fn handle_connection_inner(
 ) -> anyhow::Result<()> {
     let connection = incoming.accept()?.await?;
 
+    if connection.alpn().as_deref() == Some(ADMIN_ALPN) {
+        crate::cli::remote::handle_connection((*app_state).clone(), connection).await;
+
+        return Ok(());
+    }
+
     let node_id = connection.remote_node_id()?.to_string();
 
     let counter = semaphore
@@ -129,6 +197,12 @@ async fn drive_connection(
 ) -> anyhow::Result<()> {
     let mut event_stream = Box::pin(events(app_state.clone(), node_id.clone()).await);
 
+    let mut status_stream = Box::pin(app_state.event_bus.subscribe_to_status(node_id.clone()));
+
+    // Suspended and deleted users can still connect to learn about their
+    // status from the rejected requests, but we do not stream events to them
+    let mut streaming = !is_blocked(&app_state, &node_id).await;
+
     loop {
         tokio::select! {
             stream = connection.accept_bi() => {
@@ -141,7 +215,7 @@ async fn drive_connection(
                     }
                 }
             }
-            event = event_stream.next() => {
+            event = event_stream.next(), if streaming => {
                 let event = event.unwrap().map_err(|e| anyhow!(e))?;
 
                 let event = serde_json::to_vec(&event).expect("Failed to serialize event");
@@ -152,6 +226,22 @@ async fn drive_connection(
 
                 send.finish()?;
             }
+            _ = status_stream.next() => {
+                let blocked = is_blocked(&app_state, &node_id).await;
+
+                if blocked && streaming {
+                    info!(%node_id, "Stopping event stream of blocked user");
+
+                    streaming = false;
+                }
+
+                // The client reconnects to obtain a fresh event stream
+                if !blocked && !streaming {
+                    connection.close(0u32.into(), b"Account reactivated");
+
+                    return Ok(());
+                }
+            }
             _ = ct.cancelled() => {
                 return Ok(());
             }
@@ -159,6 +249,15 @@ async fn drive_connection(
     }
 }
 
+async fn is_blocked(app_state: &AppState, user_pk: &str) -> bool {
+    db::get_user(
+        &mut *app_state.db.get_connection().await,
+        user_pk.to_string(),
+    )
+    .await
+    .is_some_and(|user| rpc::check_not_blocked(&user).is_err())
+}
+
 async fn handle_request(
     state: Arc<AppState>,
     user_id: String,
@@ -169,7 +268,13 @@ async fn handle_request(
 
     let request: ClientRpcRequest<Value> = serde_json::from_slice(&request)?;
 
-    let response = match request.method.as_str() {
+    let metrics = state.metrics.clone();
+
+    let start = Instant::now();
+
+    let mut method = request.method.as_str();
+
+    let response = match method {
         ENDPOINT_REGISTER => client_method!(register, state, user_id, request.request, false).await,
         ENDPOINT_BOLT11_RECEIVE => {
             client_method!(bolt11_receive, state, user_id, request.request, true).await
@@ -190,9 +295,27 @@ async fn handle_request(
             client_method!(set_recovery_name, state, user_id, request.request, true).await
         }
         ENDPOINT_RECOVER => client_method!(recover, state, user_id, request.request, true).await,
-        _ => Err(format!("Method '{}' not found", request.method)),
+        ENDPOINT_CREATE_RECOVERY_CODE => {
+            client_method!(create_recovery_code, state, user_id, request.request, true).await
+        }
+        ENDPOINT_LIABILITY_PROOF => {
+            client_method!(liability_proof, state, user_id, request.request, true).await
+        }
+        ENDPOINT_RECEIVABLE_MAX => {
+            client_method!(receivable_max, state, user_id, request.request, true).await
+        }
+        ENDPOINT_MIGRATION => {
+            client_method!(migration, state, user_id, request.request, false).await
+        }
+        _ => {
+            method = "unknown";
+
+            Err(format!("Method '{}' not found", request.method))
+        }
     };
 
+    metrics.observe_rpc(method, response.is_ok(), start.elapsed());
+
     let response = serde_json::to_vec(&response).expect("Failed to serialize response");
 
     send_stream.write_all(&response).await?;
@@ -1,21 +1,27 @@
 use std::str::FromStr;
 use std::sync::Arc;
 
-use bitcoin::hashes::Hash;
+use bitcoin::hashes::{Hash, sha256};
+use bitcoin::hex::DisplayHex;
 use diesel::SqliteConnection;
 use lightning::offers::offer::Offer;
 use lightning_invoice::{Bolt11InvoiceDescription, Description};
+use rand::Rng;
 use tracing::{error, info};
 
+use puncture_cli_core::{Settings, UserStatus};
 use puncture_client_core::{
     Bolt11ReceiveRequest, Bolt11ReceiveResponse, Bolt11SendRequest, Bolt12ReceiveResponse,
-    Bolt12SendRequest, OnchainSendRequest, OnchainSendResponse, RecoverRequest, RecoverResponse,
-    RegisterRequest, RegisterResponse, SetRecoveryNameRequest,
+    Bolt12SendRequest, CreateRecoveryCodeRequest, CreateRecoveryCodeResponse,
+    InsufficientInboundLiquidity, LiabilityProof, OnchainSendRequest, OnchainSendResponse,
+    ReceivableMaxResponse, RecoverRequest, RecoverResponse, RegisterRequest, RegisterResponse,
+    SetRecoveryNameRequest,
 };
-use puncture_core::unix_time;
+use puncture_core::{NodeIdMigration, PunctureCode, unix_time};
+use puncture_daemon_db::models::{RecoveryCodeRecord, User};
 
 use super::db;
-use crate::{AppState, Args, EventBus, convert::IntoPayment};
+use crate::{AppState, EventBus, convert::IntoPayment};
 
 pub async fn register(
     app_state: Arc<AppState>,
@@ -28,14 +34,18 @@ pub async fn register(
         .await
         .ok_or("Unknown invite code".to_string())?;
 
-    if invite.expires_at < unix_time() {
-        return Err("Invite expired".to_string());
+    if let Some(user) = db::get_user(&mut conn, user_pk.clone()).await {
+        check_not_blocked(&user)?;
     }
 
     if invite.expires_at < unix_time() {
         return Err("Invite expired".to_string());
     }
 
+    if invite.revoked_at.is_some() {
+        return Err("Invite revoked".to_string());
+    }
+
     if invite.user_limit <= db::count_invite_users(&mut conn, &request.invite_id).await {
         return Err("Invite user limit reached".to_string());
     }
@@ -59,26 +69,58 @@ pub async fn bolt11_receive(
 
     let mut conn = state.db.get_connection().await;
 
+    let settings = state.settings.read().await.clone();
+
     let pending = db::count_pending_invoices(&mut conn, user_pk.clone()).await;
 
-    if pending >= state.args.max_pending_payments_per_user as i64 {
+    if pending >= settings.max_pending_payments_per_user as i64 {
         return Err("Too many pending invoices".to_string());
     }
 
-    check_amount_bounds(&state.args, request.amount_msat as u64)?;
+    check_amount_bounds(&settings, request.amount_msat as u64)?;
 
-    let invoice = state
-        .node
-        .bolt11_payment()
-        .receive(
-            request.amount_msat.into(),
-            &Description::new(request.description.clone())
-                .map(Bolt11InvoiceDescription::Direct)
-                .map_err(|e| e.to_string())?,
-            state.args.invoice_expiry_secs,
-        )
-        .inspect_err(|error| error!(?error, "ldk node bolt11 receive error"))
-        .map_err(|_| "Failed to create invoice".to_string())?;
+    let description = Description::new(request.description.clone())
+        .map(Bolt11InvoiceDescription::Direct)
+        .map_err(|e| e.to_string())?;
+
+    let receivable_max_msat = crate::lsp::receivable_max_msat(&state.node);
+
+    // Users opt into just-in-time channels by accepting a maximum opening fee
+    let (invoice, lsp_fee_msat) = match request.max_lsp_fee_msat {
+        _ if request.amount_msat as u64 <= receivable_max_msat => {
+            let invoice = state
+                .node
+                .bolt11_payment()
+                .receive(
+                    request.amount_msat.into(),
+                    &description,
+                    settings.invoice_expiry_secs,
+                )
+                .inspect_err(|error| error!(?error, "ldk node bolt11 receive error"))
+                .map_err(|_| "Failed to create invoice".to_string())?;
+
+            (invoice, None)
+        }
+        Some(max_lsp_fee_msat) if state.args.lsp2_node_id.is_some() => {
+            let (invoice, lsp_fee_msat) = crate::lsp::receive_via_jit_channel(
+                &state.node,
+                request.amount_msat.into(),
+                &description,
+                settings.invoice_expiry_secs,
+                max_lsp_fee_msat,
+            )
+            .inspect_err(|error| error!(?error, "ldk node jit channel receive error"))
+            .map_err(|e| e.to_string())?;
+
+            (invoice, Some(lsp_fee_msat))
+        }
+        _ => {
+            return Err(InsufficientInboundLiquidity {
+                receivable_max_msat,
+            }
+            .to_string());
+        }
+    };
 
     db::create_invoice(
         &mut conn,
@@ -86,11 +128,14 @@ pub async fn bolt11_receive(
         invoice.clone(),
         request.amount_msat.into(),
         request.description,
-        state.args.invoice_expiry_secs,
+        settings.invoice_expiry_secs,
     )
     .await;
 
-    Ok(Bolt11ReceiveResponse { invoice })
+    Ok(Bolt11ReceiveResponse {
+        invoice,
+        lsp_fee_msat,
+    })
 }
 
 pub async fn bolt12_receive(
@@ -100,10 +145,23 @@ pub async fn bolt12_receive(
 ) -> Result<Bolt12ReceiveResponse, String> {
     let mut conn = state.db.get_connection().await;
 
-    if let Some(record) = db::get_offer_by_user_pk(&mut conn, user_pk.clone()).await {
-        if record.created_at > unix_time() - (24 * 60 * 60 * 1000) {
-            return Ok(Bolt12ReceiveResponse { offer: record.pr });
+    let min_amount_msat = state.settings.read().await.min_amount_sats as u64 * 1000;
+
+    let receivable_max_msat = crate::lsp::receivable_max_msat(&state.node);
+
+    // Offers are reusable, so we only refuse them while we cannot receive even
+    // the smallest amount we accept
+    if receivable_max_msat < min_amount_msat {
+        return Err(InsufficientInboundLiquidity {
+            receivable_max_msat,
         }
+        .to_string());
+    }
+
+    if let Some(record) = db::get_offer_by_user_pk(&mut conn, user_pk.clone()).await
+        && record.created_at > unix_time() - (24 * 60 * 60 * 1000)
+    {
+        return Ok(Bolt12ReceiveResponse { offer: record.pr });
     }
 
     let offer = state
@@ -135,7 +193,9 @@ pub async fn bolt11_send(
 ) -> Result<(), String> {
     let mut conn = state.db.get_connection().await;
 
-    let fee_msat = check_send(&mut conn, user_pk.clone(), request.amount_msat, &state.args).await?;
+    let settings = state.settings.read().await.clone();
+
+    let fee_msat = check_send(&mut conn, user_pk.clone(), request.amount_msat, &settings).await?;
 
     match crate::db::get_invoice(&mut conn, request.invoice.payment_hash().to_byte_array()).await {
         Some(invoice) => {
@@ -143,10 +203,10 @@ pub async fn bolt11_send(
                 return Err("This is your own invoice".to_string());
             }
 
-            if let Some(amount_msat) = invoice.amount_msat {
-                if amount_msat as u64 > request.amount_msat {
-                    return Err("Amount is lower than the invoice's minimum amount".to_string());
-                }
+            if let Some(amount_msat) = invoice.amount_msat
+                && amount_msat as u64 > request.amount_msat
+            {
+                return Err("Amount is lower than the invoice's minimum amount".to_string());
             }
 
             let (send_record, receive_record) = db::create_internal_transfer(
@@ -217,7 +277,9 @@ pub async fn bolt12_send(
 ) -> Result<(), String> {
     let mut conn = state.db.get_connection().await;
 
-    let fee_msat = check_send(&mut conn, user_pk.clone(), request.amount_msat, &state.args).await?;
+    let settings = state.settings.read().await.clone();
+
+    let fee_msat = check_send(&mut conn, user_pk.clone(), request.amount_msat, &settings).await?;
 
     let offer = Offer::from_str(&request.offer).map_err(|_| "Invalid offer".to_string())?;
 
@@ -227,10 +289,10 @@ pub async fn bolt12_send(
                 return Err("This is your own payment request".to_string());
             }
 
-            if let Some(amount_msat) = offer.amount_msat {
-                if amount_msat as u64 > request.amount_msat {
-                    return Err("Amount is lower than the offer's minimum amount".to_string());
-                }
+            if let Some(amount_msat) = offer.amount_msat
+                && amount_msat as u64 > request.amount_msat
+            {
+                return Err("Amount is lower than the offer's minimum amount".to_string());
             }
 
             let (send_record, receive_record) = db::create_internal_transfer(
@@ -293,19 +355,48 @@ pub async fn bolt12_send(
     Ok(())
 }
 
+/// Reject every request of suspended and deleted users
+pub fn check_not_blocked(user: &User) -> Result<(), String> {
+    match user.status.parse::<UserStatus>()? {
+        UserStatus::Active | UserStatus::Frozen => Ok(()),
+        status => Err(status_error(status, user.status_reason.as_deref())),
+    }
+}
+
+/// Frozen users can still receive but their balance cannot leave the account
+async fn check_active(conn: &mut SqliteConnection, user_pk: String) -> Result<(), String> {
+    let user = db::get_user(conn, user_pk)
+        .await
+        .ok_or("Unknown user".to_string())?;
+
+    match user.status.parse::<UserStatus>()? {
+        UserStatus::Active => Ok(()),
+        status => Err(status_error(status, user.status_reason.as_deref())),
+    }
+}
+
+fn status_error(status: UserStatus, reason: Option<&str>) -> String {
+    match reason {
+        Some(reason) => format!("Account is {status}: {reason}"),
+        None => format!("Account is {status}"),
+    }
+}
+
 async fn check_send(
     conn: &mut SqliteConnection,
     user_pk: String,
     amount_msat: u64,
-    args: &Args,
+    settings: &Settings,
 ) -> Result<u64, String> {
+    check_active(conn, user_pk.clone()).await?;
+
     let pending_payments = db::count_pending_sends(conn, user_pk.clone()).await;
 
-    if pending_payments >= args.max_pending_payments_per_user as i64 {
+    if pending_payments >= settings.max_pending_payments_per_user as i64 {
         return Err("Too many pending payments".to_string());
     }
 
-    check_amount_bounds(args, amount_msat)?;
+    check_amount_bounds(settings, amount_msat)?;
 
     let balance_msat = crate::db::user_balance(conn, user_pk.clone()).await;
 
@@ -313,7 +404,7 @@ async fn check_send(
         return Err("Insufficient balance to cover the amount".to_string());
     }
 
-    let fee_msat = (amount_msat * args.fee_ppm) / 1_000_000 + args.base_fee_msat;
+    let fee_msat = (amount_msat * settings.fee_ppm) / 1_000_000 + settings.base_fee_msat;
 
     if balance_msat < amount_msat + fee_msat {
         return Err("Insufficient balance to cover the amount and potential fee".to_string());
@@ -322,18 +413,18 @@ async fn check_send(
     Ok(fee_msat)
 }
 
-fn check_amount_bounds(args: &Args, amount_msat: u64) -> Result<(), String> {
-    if amount_msat < args.min_amount_sats as u64 * 1000 {
+fn check_amount_bounds(settings: &Settings, amount_msat: u64) -> Result<(), String> {
+    if amount_msat < settings.min_amount_sats as u64 * 1000 {
         return Err(format!(
             "The minimum amount is {} sats",
-            args.min_amount_sats
+            settings.min_amount_sats
         ));
     }
 
-    if amount_msat > args.max_amount_sats as u64 * 1000 {
+    if amount_msat > settings.max_amount_sats as u64 * 1000 {
         return Err(format!(
             "The maximum amount is {} sats",
-            args.max_amount_sats
+            settings.max_amount_sats
         ));
     }
 
@@ -364,13 +455,17 @@ pub async fn onchain_send(
 
     let mut conn = state.db.get_connection().await;
 
+    check_active(&mut conn, user_pk.clone()).await?;
+
     let balance_msat = crate::db::user_balance(&mut conn, user_pk.clone()).await;
 
     if balance_msat < request.amount_sats * 1000 {
         return Err("Insufficient balance to cover the amount".to_string());
     }
 
-    if balance_msat < request.amount_sats * 1000 + state.args.onchain_base_fee_msat {
+    let onchain_base_fee_msat = state.settings.read().await.onchain_base_fee_msat;
+
+    if balance_msat < request.amount_sats * 1000 + onchain_base_fee_msat {
         return Err("Insufficient balance to cover the fee".to_string());
     }
 
@@ -390,7 +485,7 @@ pub async fn onchain_send(
         txid.to_byte_array(),
         user_pk.clone(),
         (request.amount_sats * 1000) as i64,
-        state.args.onchain_base_fee_msat as i64,
+        onchain_base_fee_msat as i64,
         String::new(),
         address.to_string(),
         "successful".to_string(),
@@ -445,39 +540,66 @@ pub async fn recover(
 ) -> Result<RecoverResponse, String> {
     let mut conn = app_state.db.get_connection().await;
 
-    let recovery = db::get_recovery(&mut conn, &request.recovery_id)
-        .await
-        .ok_or("Unknown recovery code".to_string())?;
+    let recovery = match db::get_recovery(&mut conn, &request.recovery_id).await {
+        Some(recovery) => {
+            if recovery.expires_at < unix_time() {
+                return Err("Recovery expired".to_string());
+            }
 
-    if recovery.expires_at < unix_time() {
-        return Err("Recovery expired".to_string());
-    }
+            (recovery.user_pk, recovery.id)
+        }
+        None => {
+            let id_hash = hash_recovery_id(&request.recovery_id);
 
-    if user_pk == recovery.user_pk {
-        return Err("You cannot recover the current user".to_string());
-    }
+            let recovery_code = db::get_recovery_code(&mut conn, &id_hash)
+                .await
+                .ok_or("Unknown recovery code".to_string())?;
+
+            if recovery_code.expires_at < unix_time() {
+                return Err("Recovery code expired".to_string());
+            }
+
+            check_recover_rate_limit(&recovery_code)?;
+
+            if let Some(pin_hash) = recovery_code.pin_hash {
+                let pin = request
+                    .pin
+                    .as_ref()
+                    .ok_or("Recovery code requires a PIN".to_string())?;
+
+                if hash_recovery_pin(&request.recovery_id, pin) != pin_hash {
+                    db::increment_recovery_code_attempts(&mut conn, &id_hash).await;
 
-    let balance_msat = crate::db::user_balance(&mut conn, recovery.user_pk.clone()).await;
+                    return Err("Invalid PIN".to_string());
+                }
+            }
 
-    if balance_msat == 0 {
-        return Err("User has no balance to recover".to_string());
+            (recovery_code.user_pk, id_hash)
+        }
+    };
+
+    let (recovery_user_pk, recovery_pr) = recovery;
+
+    if user_pk == recovery_user_pk {
+        return Err("You cannot recover the current user".to_string());
     }
 
-    let (send_record, receive_record) = db::create_internal_transfer(
+    check_active(&mut conn, recovery_user_pk.clone()).await?;
+
+    let (send_record, receive_record) = db::recover_balance(
         &mut conn,
-        recovery.user_pk.clone(),
+        recovery_user_pk.clone(),
         user_pk.clone(),
-        balance_msat as i64,
-        0,
-        recovery.id.clone(),
-        "Recovery".to_string(),
+        recovery_pr,
     )
-    .await;
+    .await?;
+
+    let balance_msat = receive_record.amount_msat as u64;
 
     push_events(
         &mut conn,
         app_state.event_bus.clone(),
-        recovery.user_pk.clone(),
+        recovery_user_pk.clone(),
         send_record.into_payment(true),
     )
     .await;
@@ -492,3 +614,111 @@ pub async fn recover(
 
     Ok(RecoverResponse { balance_msat })
 }
+
+pub async fn create_recovery_code(
+    app_state: Arc<AppState>,
+    user_pk: String,
+    request: CreateRecoveryCodeRequest,
+) -> Result<CreateRecoveryCodeResponse, String> {
+    if let Some(pin) = request.pin.as_ref()
+        && (!(4..=12).contains(&pin.len()) || !pin.chars().all(|c| c.is_ascii_digit()))
+    {
+        return Err("PIN must consist of 4 to 12 digits".to_string());
+    }
+
+    let recovery_id: [u8; 16] = rand::rng().random();
+
+    let recovery_id_hex = recovery_id.as_hex().to_string();
+
+    db::create_recovery_code(
+        &mut *app_state.db.get_connection().await,
+        user_pk,
+        hash_recovery_id(&recovery_id_hex),
+        request
+            .pin
+            .map(|pin| hash_recovery_pin(&recovery_id_hex, &pin)),
+        unix_time() + RECOVERY_CODE_EXPIRY_MS,
+    )
+    .await;
+
+    Ok(CreateRecoveryCodeResponse {
+        recovery_code: PunctureCode::recovery(recovery_id).encode(),
+    })
+}
+
+pub async fn liability_proof(
+    app_state: Arc<AppState>,
+    user_pk: String,
+    _request: (),
+) -> Result<LiabilityProof, String> {
+    app_state
+        .liability_tree
+        .read()
+        .await
+        .as_ref()
+        .ok_or("No liabilities have been published yet".to_string())?
+        .proof(&user_pk)
+        .ok_or("You registered after the liabilities were published".to_string())
+}
+
+pub async fn receivable_max(
+    state: Arc<AppState>,
+    _user_pk: String,
+    _request: (),
+) -> Result<ReceivableMaxResponse, String> {
+    Ok(ReceivableMaxResponse {
+        receivable_max_msat: crate::lsp::receivable_max_msat(&state.node),
+    })
+}
+
+/// Clients query every node id they connect to for a migration statement,
+/// which is only served under the node ids of retired secret keys
+pub async fn migration(
+    _state: Arc<AppState>,
+    _user_pk: String,
+    _request: (),
+) -> Result<Option<NodeIdMigration>, String> {
+    Ok(None)
+}
+
+/// Number of invalid PINs after which further attempts on a user created
+/// recovery code are only allowed once per rate limit window
+const MAX_RECOVER_ATTEMPTS: i64 = 5;
+
+const RECOVER_WINDOW_MS: i64 = 60 * 60 * 1000;
+
+/// Number of invalid PINs after which a user created recovery code is locked
+const MAX_RECOVERY_PIN_ATTEMPTS: i64 = 10;
+
+/// User created recovery codes expire after a year and have to be recreated
+const RECOVERY_CODE_EXPIRY_MS: i64 = 365 * 24 * 60 * 60 * 1000;
+
+/// The failed attempts are persisted with the recovery code, such that neither
+/// registering fresh identities nor a restart of the daemon resets the limit
+fn check_recover_rate_limit(recovery_code: &RecoveryCodeRecord) -> Result<(), String> {
+    if recovery_code.failed_attempts >= MAX_RECOVERY_PIN_ATTEMPTS {
+        return Err("Recovery code is locked after too many invalid PINs".to_string());
+    }
+
+    if recovery_code.failed_attempts >= MAX_RECOVER_ATTEMPTS
+        && recovery_code
+            .last_failed_at
+            .is_some_and(|last_failed_at| last_failed_at > unix_time() - RECOVER_WINDOW_MS)
+    {
+        return Err("Too many recovery attempts, please try again later".to_string());
+    }
+
+    Ok(())
+}
+
+/// User created recovery codes are only stored hashed such that a leaked
+/// database does not allow anyone to drain the balances of our users.
+fn hash_recovery_id(recovery_id: &str) -> String {
+    sha256::Hash::hash(recovery_id.as_bytes()).to_string()
+}
+
+/// The PIN is hashed together with the recovery id such that it cannot be
+/// brute forced from the database without knowing the recovery code.
+fn hash_recovery_pin(recovery_id: &str, pin: &str) -> String {
+    sha256::Hash::hash(format!("{recovery_id}:{pin}").as_bytes()).to_string()
+}
@@ -0,0 +1,94 @@
+use std::fs;
+
+use anyhow::{Context, Result, ensure};
+use serde::Deserialize;
+use tokio::signal::unix::{SignalKind, signal};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use puncture_cli_core::Settings;
+
+use crate::{AppState, Args};
+
+/// The settings read from the config file, every setting is optional
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    fee_ppm: Option<u64>,
+    base_fee_msat: Option<u64>,
+    onchain_base_fee_msat: Option<u64>,
+    invoice_expiry_secs: Option<u32>,
+    min_amount_sats: Option<u32>,
+    max_amount_sats: Option<u32>,
+    max_pending_payments_per_user: Option<u32>,
+}
+
+/// Resolve the settings from the env vars and CLI flags, falling back to the
+/// config file and then to the defaults
+pub fn load(args: &Args) -> Result<Settings> {
+    let file = match &args.config_file {
+        Some(path) => toml::from_str::<ConfigFile>(
+            &fs::read_to_string(path).context("Failed to read config file")?,
+        )
+        .context("Failed to parse config file")?,
+        None => ConfigFile::default(),
+    };
+
+    let settings = Settings {
+        fee_ppm: args.fee_ppm.or(file.fee_ppm).unwrap_or(5000),
+        base_fee_msat: args.base_fee_msat.or(file.base_fee_msat).unwrap_or(10_000),
+        onchain_base_fee_msat: args
+            .onchain_base_fee_msat
+            .or(file.onchain_base_fee_msat)
+            .unwrap_or(5_000_000),
+        invoice_expiry_secs: args
+            .invoice_expiry_secs
+            .or(file.invoice_expiry_secs)
+            .unwrap_or(3600),
+        min_amount_sats: args.min_amount_sats.or(file.min_amount_sats).unwrap_or(1),
+        max_amount_sats: args
+            .max_amount_sats
+            .or(file.max_amount_sats)
+            .unwrap_or(100_000),
+        max_pending_payments_per_user: args
+            .max_pending_payments_per_user
+            .or(file.max_pending_payments_per_user)
+            .unwrap_or(10),
+    };
+
+    ensure!(
+        settings.min_amount_sats <= settings.max_amount_sats,
+        "The minimum amount exceeds the maximum amount"
+    );
+
+    Ok(settings)
+}
+
+/// Re-read the config file and swap the settings used by new requests, the
+/// current settings stay in place if the file is invalid
+pub async fn reload(state: &AppState) -> Result<Settings> {
+    let settings = load(&state.args)?;
+
+    *state.settings.write().await = settings.clone();
+
+    info!(?settings, "Reloaded settings");
+
+    Ok(settings)
+}
+
+pub async fn run_reload(app_state: AppState, ct: CancellationToken) {
+    let mut hangup = signal(SignalKind::hangup()).expect("Failed to install SIGHUP handler");
+
+    loop {
+        tokio::select! {
+            _ = hangup.recv() => {
+                if let Err(e) = reload(&app_state).await {
+                    warn!(?e, "Failed to reload config file");
+                }
+            }
+            _ = ct.cancelled() => {
+                break;
+            }
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use bitcoin::hex::DisplayHex;
 use puncture_client_core::Payment;
-use puncture_daemon_db::models::{InvoiceRecord, OfferRecord, ReceiveRecord, SendRecord};
+use puncture_daemon_db::models::{
+    AdjustmentRecord, InvoiceRecord, OfferRecord, ReceiveRecord, SendRecord,
+};
 
 use puncture_core::unix_time;
 
@@ -40,6 +42,22 @@ impl IntoPayment for SendRecord {
     }
 }
 
+impl IntoPayment for AdjustmentRecord {
+    fn into_payment(self, is_live: bool) -> Payment {
+        Payment {
+            id: self.id,
+            payment_type: "adjustment".to_string(),
+            is_live,
+            amount_msat: self.amount_msat,
+            fee_msat: 0,
+            description: self.memo,
+            ln_address: None,
+            status: "successful".to_string(),
+            created_at: self.created_at,
+        }
+    }
+}
+
 pub trait IntoReceiveRecord {
     fn into_receive_record(self, id: [u8; 32], amount_msat: u64) -> ReceiveRecord;
 }
@@ -1,10 +1,32 @@
 use bitcoin::hex::DisplayHex;
-use diesel::SqliteConnection;
-use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+use diesel::result::DatabaseErrorKind;
+use diesel::{
+    Connection, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, TextExpressionMethods,
+};
+use diesel::{QueryResult, SqliteConnection};
 
-use puncture_daemon_db::models::{InvoiceRecord, OfferRecord, ReceiveRecord, SendRecord};
-use puncture_daemon_db::schema::{invoice, offer, receive, send};
-use tracing::info;
+use puncture_cli_core::{
+    AuditEntry, AuditListRequest, ChannelOrderInfo, InviteInfo, UserInfo, UserStatus,
+};
+use puncture_core::{NodeIdMigration, unix_time};
+use puncture_daemon_db::models::{
+    AdjustmentRecord, AuditLogRecord, ChannelOrderRecord, ForwardRecord, InviteRecord,
+    InvoiceRecord, LiabilityLeafRecord, LiabilityPublicationRecord, LiquidityDecisionRecord,
+    MigrationRecord, OfferRecord, ReceiveRecord, RecoveryCodeRecord, SendRecord, User,
+};
+use puncture_daemon_db::schema::{
+    adjustment, audit_log, channel_order, forward, invite, invoice, liability_leaf,
+    liability_publication, liquidity_decision, migration, offer, receive, recovery_code, send,
+    user,
+};
+use rand::Rng;
+use tracing::{info, warn};
+
+use crate::archive::Accounts;
+
+/// Maximum number of rows inserted per statement, SQLite limits the number of
+/// variables bound to a single statement to 32766
+const INSERT_CHUNK_SIZE: usize = 1000;
 
 pub async fn get_invoice(
     conn: &mut SqliteConnection,
@@ -62,11 +84,22 @@ pub async fn create_receive_payment(conn: &mut SqliteConnection, record: Receive
 }
 
 pub async fn user_balance(conn: &mut SqliteConnection, user_pk: String) -> u64 {
+    let balance_msat = balance_msat(conn, &user_pk).expect("Failed to query user balance");
+
+    if balance_msat < 0 {
+        warn!(?user_pk, ?balance_msat, "User balance is negative");
+    }
+
+    balance_msat.max(0) as u64
+}
+
+/// The signed balance of a user, usable inside a transaction such that it can
+/// be checked before moving funds without racing concurrent payments
+pub fn balance_msat(conn: &mut SqliteConnection, user_pk: &str) -> QueryResult<i64> {
     let receive_sum: i64 = receive::table
-        .filter(receive::user_pk.eq(user_pk.clone()))
+        .filter(receive::user_pk.eq(user_pk))
         .select(receive::amount_msat)
-        .load::<i64>(conn)
-        .unwrap_or_default()
+        .load::<i64>(conn)?
         .into_iter()
         .sum();
 
@@ -74,11 +107,554 @@ pub async fn user_balance(conn: &mut SqliteConnection, user_pk: String) -> u64 {
         .filter(send::user_pk.eq(user_pk))
         .filter(send::status.ne("failed"))
         .select((send::amount_msat, send::fee_msat))
-        .load::<(i64, i64)>(conn)
-        .unwrap_or_default()
+        .load::<(i64, i64)>(conn)?
         .into_iter()
         .map(|(amount, fee)| amount + fee)
         .sum();
 
-    receive_sum.saturating_sub(send_sum) as u64
+    let adjustment_sum: i64 = adjustment::table
+        .filter(adjustment::user_pk.eq(user_pk))
+        .select(adjustment::amount_msat)
+        .load::<i64>(conn)?
+        .into_iter()
+        .sum();
+
+    Ok(receive_sum + adjustment_sum - send_sum)
+}
+
+pub async fn list_user_balances(conn: &mut SqliteConnection) -> Vec<(String, u64)> {
+    let user_pks = user::table
+        .select(user::user_pk)
+        .load::<String>(conn)
+        .expect("Failed to load users");
+
+    let mut balances = Vec::with_capacity(user_pks.len());
+
+    for user_pk in user_pks {
+        let balance_msat = user_balance(conn, user_pk.clone()).await;
+
+        balances.push((user_pk, balance_msat));
+    }
+
+    balances
+}
+
+/// Credit or debit the balance of a user. The balance is checked and the
+/// adjustment recorded within one transaction, such that a concurrent payment
+/// cannot push the balance below zero. Returns the adjustment and the new balance.
+pub async fn adjust_balance(
+    conn: &mut SqliteConnection,
+    user_pk: String,
+    amount_msat: i64,
+    memo: String,
+) -> Result<(AdjustmentRecord, u64), String> {
+    if amount_msat == 0 {
+        return Err("Amount must not be zero".to_string());
+    }
+
+    if memo.trim().is_empty() {
+        return Err("Memo must not be empty".to_string());
+    }
+
+    conn.immediate_transaction::<_, diesel::result::Error, _>(|conn| {
+        let Some(user) = user::table
+            .filter(user::user_pk.eq(&user_pk))
+            .first::<User>(conn)
+            .optional()?
+        else {
+            return Ok(Err("User does not exist".to_string()));
+        };
+
+        if user.status == UserStatus::Deleted.as_str() {
+            return Ok(Err(
+                "Cannot adjust the balance of a deleted user".to_string()
+            ));
+        }
+
+        let balance_msat = balance_msat(conn, &user_pk)? + amount_msat;
+
+        if balance_msat < 0 {
+            return Ok(Err("Debit exceeds the user's balance".to_string()));
+        }
+
+        let new_adjustment = AdjustmentRecord {
+            id: rand::rng().random::<[u8; 32]>().as_hex().to_string(),
+            user_pk: user_pk.clone(),
+            amount_msat,
+            memo,
+            created_at: unix_time(),
+        };
+
+        info!(?new_adjustment, "Creating balance adjustment");
+
+        diesel::insert_into(adjustment::table)
+            .values(&new_adjustment)
+            .execute(conn)?;
+
+        Ok(Ok((new_adjustment, balance_msat as u64)))
+    })
+    .expect("Failed to adjust balance")
+}
+
+pub async fn list_migrations(conn: &mut SqliteConnection) -> Vec<NodeIdMigration> {
+    migration::table
+        .load::<MigrationRecord>(conn)
+        .expect("Failed to load migrations")
+        .into_iter()
+        .map(|record| NodeIdMigration {
+            old_node_id: record
+                .old_node_id
+                .parse()
+                .expect("Invalid node id in database"),
+            new_node_id: record
+                .new_node_id
+                .parse()
+                .expect("Invalid node id in database"),
+            created_at: record.created_at,
+            signature: record.signature,
+        })
+        .collect()
+}
+
+pub async fn create_audit_entry(
+    conn: &mut SqliteConnection,
+    actor: String,
+    action: String,
+    request: String,
+    status: u16,
+    result: Option<String>,
+) {
+    let new_entry = AuditLogRecord {
+        id: rand::rng().random::<[u8; 16]>().as_hex().to_string(),
+        actor,
+        action,
+        request,
+        status: status as i32,
+        result,
+        created_at: unix_time(),
+    };
+
+    info!(
+        actor = new_entry.actor,
+        action = new_entry.action,
+        status,
+        "Creating audit entry"
+    );
+
+    diesel::insert_into(audit_log::table)
+        .values(&new_entry)
+        .execute(conn)
+        .expect("Failed to create audit entry");
+}
+
+pub async fn list_audit_entries(
+    conn: &mut SqliteConnection,
+    request: AuditListRequest,
+) -> Vec<AuditEntry> {
+    let mut query = audit_log::table.into_boxed();
+
+    if let Some(actor) = request.actor {
+        query = query.filter(audit_log::actor.like(format!("{actor}%")));
+    }
+
+    if let Some(action) = request.action {
+        query = query.filter(audit_log::action.eq(action));
+    }
+
+    if let Some(since_days) = request.since_days {
+        query =
+            query.filter(audit_log::created_at.ge(unix_time() - since_days as i64 * 86_400_000));
+    }
+
+    if request.failed {
+        query = query.filter(audit_log::status.ge(400));
+    }
+
+    query
+        .order(audit_log::created_at.desc())
+        .limit(request.limit as i64)
+        .load::<AuditLogRecord>(conn)
+        .expect("Failed to load audit entries")
+        .into_iter()
+        .map(|record| AuditEntry {
+            id: record.id,
+            actor: record.actor,
+            action: record.action,
+            request: record.request,
+            status: record.status as u16,
+            result: record.result,
+            created_at: record.created_at,
+        })
+        .collect()
+}
+
+fn invite_info(conn: &mut SqliteConnection, record: InviteRecord) -> InviteInfo {
+    let user_count = user::table
+        .filter(user::invite_id.eq(record.id.clone()))
+        .count()
+        .first::<i64>(conn)
+        .expect("Failed to count invite users");
+
+    InviteInfo {
+        id: record.id,
+        user_limit: record.user_limit as u32,
+        user_count: user_count as u32,
+        expires_at: record.expires_at,
+        created_at: record.created_at,
+        revoked_at: record.revoked_at,
+    }
+}
+
+pub async fn list_invites(conn: &mut SqliteConnection) -> Vec<InviteInfo> {
+    invite::table
+        .order(invite::created_at.desc())
+        .load::<InviteRecord>(conn)
+        .expect("Failed to load invites")
+        .into_iter()
+        .map(|record| invite_info(conn, record))
+        .collect()
+}
+
+pub async fn get_invite(conn: &mut SqliteConnection, invite_id: &str) -> Option<InviteInfo> {
+    invite::table
+        .filter(invite::id.eq(invite_id.to_string()))
+        .first::<InviteRecord>(conn)
+        .optional()
+        .expect("Failed to query invite")
+        .map(|record| invite_info(conn, record))
+}
+
+pub async fn list_invite_users(conn: &mut SqliteConnection, invite_id: &str) -> Vec<String> {
+    user::table
+        .filter(user::invite_id.eq(invite_id.to_string()))
+        .order(user::created_at.asc())
+        .select(user::user_pk)
+        .load::<String>(conn)
+        .expect("Failed to load invite users")
+}
+
+/// Revoke an invite, returns false if it does not exist or was already revoked
+pub async fn revoke_invite(conn: &mut SqliteConnection, invite_id: &str) -> bool {
+    info!(?invite_id, "Revoking invite");
+
+    diesel::update(
+        invite::table
+            .filter(invite::id.eq(invite_id.to_string()))
+            .filter(invite::revoked_at.is_null()),
+    )
+    .set(invite::revoked_at.eq(unix_time()))
+    .execute(conn)
+    .expect("Failed to revoke invite")
+        == 1
+}
+
+/// Look up a single user together with their balance
+pub async fn get_user_info(conn: &mut SqliteConnection, user_pk: String) -> Option<UserInfo> {
+    let user_record = user::table
+        .filter(user::user_pk.eq(user_pk))
+        .first::<User>(conn)
+        .optional()
+        .expect("Failed to query user")?;
+
+    Some(UserInfo {
+        user_pk: user_record.user_pk.clone(),
+        balance_msat: user_balance(conn, user_record.user_pk).await,
+        recovery_name: user_record.recovery_name,
+        created_at: user_record.created_at,
+        status: user_record
+            .status
+            .parse()
+            .expect("Invalid user status in database"),
+        status_reason: user_record.status_reason,
+    })
+}
+
+pub async fn set_user_status(
+    conn: &mut SqliteConnection,
+    user_pk: String,
+    status: UserStatus,
+    reason: Option<String>,
+) {
+    info!(?user_pk, ?status, ?reason, "Setting user status");
+
+    diesel::update(user::table.filter(user::user_pk.eq(user_pk)))
+        .set((
+            user::status.eq(status.to_string()),
+            user::status_reason.eq(reason),
+        ))
+        .execute(conn)
+        .expect("Failed to set user status");
+}
+
+pub async fn count_all_pending_sends(conn: &mut SqliteConnection) -> i64 {
+    send::table
+        .filter(send::status.eq("pending"))
+        .count()
+        .first::<i64>(conn)
+        .expect("Failed to count pending sends")
+}
+
+pub async fn load_accounts(conn: &mut SqliteConnection) -> Accounts {
+    Accounts {
+        users: user::table
+            .load::<User>(conn)
+            .expect("Failed to load users"),
+        invites: invite::table
+            .load::<InviteRecord>(conn)
+            .expect("Failed to load invites"),
+        receives: receive::table
+            .load::<ReceiveRecord>(conn)
+            .expect("Failed to load receives"),
+        sends: send::table
+            .load::<SendRecord>(conn)
+            .expect("Failed to load sends"),
+        adjustments: adjustment::table
+            .load::<AdjustmentRecord>(conn)
+            .expect("Failed to load adjustments"),
+        recovery_codes: recovery_code::table
+            .load::<RecoveryCodeRecord>(conn)
+            .expect("Failed to load recovery codes"),
+    }
+}
+
+/// Insert all accounts of an archive or none of them, an archive conflicting
+/// with existing rows such as an invite created by the operator is rejected
+pub async fn insert_accounts(
+    conn: &mut SqliteConnection,
+    accounts: &Accounts,
+) -> Result<(), String> {
+    conn.transaction(|conn| {
+        for chunk in accounts.invites.chunks(INSERT_CHUNK_SIZE) {
+            diesel::insert_into(invite::table)
+                .values(chunk)
+                .execute(conn)?;
+        }
+
+        for chunk in accounts.users.chunks(INSERT_CHUNK_SIZE) {
+            diesel::insert_into(user::table)
+                .values(chunk)
+                .execute(conn)?;
+        }
+
+        for chunk in accounts.receives.chunks(INSERT_CHUNK_SIZE) {
+            diesel::insert_into(receive::table)
+                .values(chunk)
+                .execute(conn)?;
+        }
+
+        for chunk in accounts.sends.chunks(INSERT_CHUNK_SIZE) {
+            diesel::insert_into(send::table)
+                .values(chunk)
+                .execute(conn)?;
+        }
+
+        for chunk in accounts.adjustments.chunks(INSERT_CHUNK_SIZE) {
+            diesel::insert_into(adjustment::table)
+                .values(chunk)
+                .execute(conn)?;
+        }
+
+        for chunk in accounts.recovery_codes.chunks(INSERT_CHUNK_SIZE) {
+            diesel::insert_into(recovery_code::table)
+                .values(chunk)
+                .execute(conn)?;
+        }
+
+        Ok::<(), diesel::result::Error>(())
+    })
+    .map_err(|e| match e {
+        diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, info) => {
+            format!("Archive conflicts with existing data: {}", info.message())
+        }
+        e => format!("Failed to import accounts: {e}"),
+    })
+}
+
+pub async fn create_channel_order(conn: &mut SqliteConnection, record: ChannelOrderRecord) {
+    info!(?record, "Creating channel order");
+
+    diesel::insert_into(channel_order::table)
+        .values(&record)
+        .execute(conn)
+        .expect("Failed to create channel order");
+}
+
+pub async fn get_channel_order(
+    conn: &mut SqliteConnection,
+    order_id: &str,
+) -> Option<ChannelOrderRecord> {
+    channel_order::table
+        .find(order_id.to_string())
+        .first::<ChannelOrderRecord>(conn)
+        .optional()
+        .expect("Failed to query channel order")
+}
+
+pub async fn list_channel_orders(conn: &mut SqliteConnection) -> Vec<ChannelOrderInfo> {
+    channel_order::table
+        .order(channel_order::created_at.desc())
+        .load::<ChannelOrderRecord>(conn)
+        .expect("Failed to load channel orders")
+        .into_iter()
+        .map(channel_order_info)
+        .collect()
+}
+
+/// Update an order with the state last reported by the LSP
+pub async fn update_channel_order(
+    conn: &mut SqliteConnection,
+    order_id: &str,
+    payment_state: &str,
+    funding_outpoint: Option<String>,
+) {
+    info!(
+        ?order_id,
+        ?payment_state,
+        ?funding_outpoint,
+        "Updating channel order"
+    );
+
+    diesel::update(channel_order::table.find(order_id.to_string()))
+        .set((
+            channel_order::payment_state.eq(payment_state.to_string()),
+            channel_order::funding_outpoint.eq(funding_outpoint),
+            channel_order::updated_at.eq(unix_time()),
+        ))
+        .execute(conn)
+        .expect("Failed to update channel order");
+}
+
+/// Record our payment of an order, returns false if we already paid it
+pub async fn set_channel_order_payment(
+    conn: &mut SqliteConnection,
+    order_id: &str,
+    payment: String,
+) -> bool {
+    info!(?order_id, ?payment, "Paying channel order");
+
+    diesel::update(
+        channel_order::table
+            .filter(channel_order::id.eq(order_id.to_string()))
+            .filter(channel_order::payment.is_null()),
+    )
+    .set((
+        channel_order::payment.eq(payment),
+        channel_order::updated_at.eq(unix_time()),
+    ))
+    .execute(conn)
+    .expect("Failed to pay channel order")
+        == 1
+}
+
+pub fn channel_order_info(record: ChannelOrderRecord) -> ChannelOrderInfo {
+    ChannelOrderInfo {
+        order_id: record.id,
+        lsp_node_id: record.lsp_node_id,
+        lsp_balance_sat: record.lsp_balance_sat as u64,
+        client_balance_sat: record.client_balance_sat as u64,
+        channel_expiry_blocks: record.channel_expiry_blocks as u32,
+        announce_channel: record.announce_channel,
+        fee_total_sat: record.fee_total_sat as u64,
+        order_total_sat: record.order_total_sat as u64,
+        invoice: record.invoice,
+        onchain_address: record.onchain_address,
+        payment_state: record.payment_state,
+        payment: record.payment,
+        funding_outpoint: record.funding_outpoint,
+        created_at: record.created_at,
+        updated_at: record.updated_at,
+    }
+}
+
+pub async fn create_liquidity_decision(
+    conn: &mut SqliteConnection,
+    record: LiquidityDecisionRecord,
+) {
+    info!(?record, "Creating liquidity decision");
+
+    diesel::insert_into(liquidity_decision::table)
+        .values(&record)
+        .execute(conn)
+        .expect("Failed to create liquidity decision");
+}
+
+pub async fn list_liquidity_decisions(
+    conn: &mut SqliteConnection,
+    limit: u32,
+) -> Vec<LiquidityDecisionRecord> {
+    liquidity_decision::table
+        .order(liquidity_decision::created_at.desc())
+        .limit(limit as i64)
+        .load::<LiquidityDecisionRecord>(conn)
+        .expect("Failed to load liquidity decisions")
+}
+
+pub async fn create_forward(conn: &mut SqliteConnection, record: ForwardRecord) {
+    info!(?record, "Creating forward");
+
+    diesel::insert_into(forward::table)
+        .values(&record)
+        .execute(conn)
+        .expect("Failed to create forward");
+}
+
+pub async fn list_forwards(
+    conn: &mut SqliteConnection,
+    since_days: Option<u32>,
+) -> Vec<ForwardRecord> {
+    let mut query = forward::table.into_boxed();
+
+    if let Some(since_days) = since_days {
+        query = query.filter(forward::created_at.ge(unix_time() - since_days as i64 * 86_400_000));
+    }
+
+    query
+        .order(forward::created_at.desc())
+        .load::<ForwardRecord>(conn)
+        .expect("Failed to load forwards")
+}
+
+/// Replace the published liability tree such that users can still obtain
+/// inclusion proofs for its root after a restart
+pub async fn save_liability_tree(
+    conn: &mut SqliteConnection,
+    publication: LiabilityPublicationRecord,
+    leaves: Vec<LiabilityLeafRecord>,
+) -> Result<(), String> {
+    info!(?publication, "Saving liability tree");
+
+    conn.transaction(|conn| {
+        diesel::delete(liability_publication::table).execute(conn)?;
+
+        diesel::delete(liability_leaf::table).execute(conn)?;
+
+        diesel::insert_into(liability_publication::table)
+            .values(&publication)
+            .execute(conn)?;
+
+        for chunk in leaves.chunks(INSERT_CHUNK_SIZE) {
+            diesel::insert_into(liability_leaf::table)
+                .values(chunk)
+                .execute(conn)?;
+        }
+
+        Ok::<(), diesel::result::Error>(())
+    })
+    .map_err(|e| format!("Failed to save liability tree: {e}"))
+}
+
+pub async fn get_liability_tree(
+    conn: &mut SqliteConnection,
+) -> Option<(LiabilityPublicationRecord, Vec<LiabilityLeafRecord>)> {
+    let publication = liability_publication::table
+        .first::<LiabilityPublicationRecord>(conn)
+        .optional()
+        .expect("Failed to query liability publication")?;
+
+    let leaves = liability_leaf::table
+        .order(liability_leaf::leaf_index.asc())
+        .load::<LiabilityLeafRecord>(conn)
+        .expect("Failed to load liability leaves");
+
+    Some((publication, leaves))
 }
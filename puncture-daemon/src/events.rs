@@ -1,3 +1,7 @@
+use std::future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
 use futures::StreamExt;
 use tokio::sync::broadcast;
 use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
@@ -9,15 +13,25 @@ use puncture_client_core::{AppEvent, Balance, Payment, Update};
 #[derive(Clone)]
 pub struct EventBus {
     tx: broadcast::Sender<(String, AppEvent)>,
+    /// Notifies the connections of a user whose status has been changed
+    status_tx: broadcast::Sender<String>,
+    /// Number of times a subscriber fell behind and missed events
+    lagged: Arc<AtomicU64>,
 }
 
 impl EventBus {
     pub fn new(capacity: usize) -> Self {
         Self {
             tx: broadcast::channel(capacity).0,
+            status_tx: broadcast::channel(capacity).0,
+            lagged: Arc::new(AtomicU64::new(0)),
         }
     }
 
+    pub fn lagged(&self) -> u64 {
+        self.lagged.load(Ordering::Relaxed)
+    }
+
     pub fn send_balance_event(&self, user_id: String, amount_msat: u64) {
         trace!(?user_id, ?amount_msat, "Balance event");
 
@@ -47,16 +61,40 @@ impl EventBus {
             .ok();
     }
 
+    pub fn send_status_event(&self, user_id: String) {
+        trace!(?user_id, "Status event");
+
+        self.status_tx.send(user_id).ok();
+    }
+
+    /// Yields whenever the status of the user may have changed, including when
+    /// the subscriber fell behind and might have missed a status event
+    pub fn subscribe_to_status(
+        &self,
+        user_id: String,
+    ) -> impl Stream<Item = ()> + Send + 'static + use<> {
+        BroadcastStream::new(self.status_tx.subscribe()).filter_map(move |r| {
+            future::ready(match r {
+                Ok(event_user_id) => (event_user_id == user_id).then_some(()),
+                Err(..) => Some(()),
+            })
+        })
+    }
+
     pub fn subscribe_to_events(
         &self,
         user_id: String,
     ) -> impl Stream<Item = Result<AppEvent, String>> + Send + 'static + use<> {
-        BroadcastStream::new(self.tx.subscribe()).filter_map(move |r| filter(user_id.clone(), r))
+        let lagged = self.lagged.clone();
+
+        BroadcastStream::new(self.tx.subscribe())
+            .filter_map(move |r| filter(user_id.clone(), lagged.clone(), r))
     }
 }
 
 async fn filter<T>(
     user_id: String,
+    lagged: Arc<AtomicU64>,
     result: Result<(String, T), BroadcastStreamRecvError>,
 ) -> Option<Result<T, String>> {
     match result {
@@ -67,6 +105,10 @@ async fn filter<T>(
                 None
             }
         }
-        Err(e) => Some(Err(e.to_string())),
+        Err(e) => {
+            lagged.fetch_add(1, Ordering::Relaxed);
+
+            Some(Err(e.to_string()))
+        }
     }
 }
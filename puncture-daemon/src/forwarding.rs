@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result, anyhow};
+use bitcoin::hex::FromHex;
+use ldk_node::UserChannelId;
+use ldk_node::config::{ChannelConfig, MaxDustHTLCExposure};
+use tracing::info;
+
+use puncture_cli_core::{
+    ChannelConfigInfo, ChannelForwardingSummary, ForwardingReportResponse,
+    UpdateChannelConfigRequest,
+};
+
+use crate::AppState;
+
+pub fn channel_config_info(config: &ChannelConfig) -> ChannelConfigInfo {
+    let (max_dust_htlc_exposure_msat, max_dust_htlc_exposure_multiplier) =
+        match config.max_dust_htlc_exposure {
+            MaxDustHTLCExposure::FixedLimit { limit_msat } => (Some(limit_msat), None),
+            MaxDustHTLCExposure::FeeRateMultiplier { multiplier } => (None, Some(multiplier)),
+        };
+
+    ChannelConfigInfo {
+        forwarding_fee_base_msat: config.forwarding_fee_base_msat,
+        forwarding_fee_proportional_millionths: config.forwarding_fee_proportional_millionths,
+        cltv_expiry_delta: config.cltv_expiry_delta,
+        max_dust_htlc_exposure_msat,
+        max_dust_htlc_exposure_multiplier,
+    }
+}
+
+/// Apply the given settings on top of the current config of the channel, the
+/// updated forwarding policy is broadcast to the network
+pub fn update_channel_config(
+    state: &AppState,
+    request: &UpdateChannelConfigRequest,
+) -> Result<ChannelConfigInfo> {
+    let user_channel_id = <[u8; 16]>::from_hex(&request.user_channel_id)
+        .map(u128::from_be_bytes)
+        .map(UserChannelId)
+        .map_err(|_| anyhow!("Invalid channel ID format"))?;
+
+    let mut config = state
+        .node
+        .list_channels()
+        .into_iter()
+        .find(|channel| {
+            channel.user_channel_id == user_channel_id
+                && channel.counterparty_node_id == request.counterparty_node_id
+        })
+        .context("Unknown channel")?
+        .config;
+
+    if let Some(base_msat) = request.forwarding_fee_base_msat {
+        config.forwarding_fee_base_msat = base_msat;
+    }
+
+    if let Some(proportional_millionths) = request.forwarding_fee_proportional_millionths {
+        config.forwarding_fee_proportional_millionths = proportional_millionths;
+    }
+
+    if let Some(cltv_expiry_delta) = request.cltv_expiry_delta {
+        config.cltv_expiry_delta = cltv_expiry_delta;
+    }
+
+    if let Some(limit_msat) = request.max_dust_htlc_exposure_msat {
+        config.max_dust_htlc_exposure = MaxDustHTLCExposure::FixedLimit { limit_msat };
+    }
+
+    if let Some(multiplier) = request.max_dust_htlc_exposure_multiplier {
+        config.max_dust_htlc_exposure = MaxDustHTLCExposure::FeeRateMultiplier { multiplier };
+    }
+
+    state
+        .node
+        .update_channel_config(&user_channel_id, request.counterparty_node_id, config)
+        .map_err(|e| anyhow!("Failed to update channel config: {e}"))?;
+
+    info!(?request, "Updated channel config");
+
+    Ok(channel_config_info(&config))
+}
+
+pub async fn report(state: &AppState, since_days: Option<u32>) -> ForwardingReportResponse {
+    let forwards =
+        crate::db::list_forwards(&mut *state.db.get_connection().await, since_days).await;
+
+    let mut channels = BTreeMap::<String, ChannelForwardingSummary>::new();
+
+    for forward in &forwards {
+        let summary = channels
+            .entry(forward.next_channel_id.clone())
+            .or_insert_with(|| ChannelForwardingSummary {
+                channel_id: forward.next_channel_id.clone(),
+                forwards: 0,
+                fee_earned_msat: 0,
+                amount_forwarded_msat: 0,
+            });
+
+        summary.forwards += 1;
+        summary.fee_earned_msat += forward.fee_earned_msat.unwrap_or(0) as u64;
+        summary.amount_forwarded_msat += forward.amount_forwarded_msat.unwrap_or(0) as u64;
+    }
+
+    let mut channels = channels.into_values().collect::<Vec<_>>();
+
+    channels.sort_by_key(|summary| std::cmp::Reverse(summary.fee_earned_msat));
+
+    ForwardingReportResponse {
+        forwards: forwards.len() as u64,
+        fee_earned_msat: channels.iter().map(|c| c.fee_earned_msat).sum(),
+        amount_forwarded_msat: channels.iter().map(|c| c.amount_forwarded_msat).sum(),
+        channels,
+    }
+}
@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use axum::{Json, Router, extract::State, http::StatusCode, routing::get};
+use diesel::RunQueryDsl;
+use iroh::Watcher;
+
+use puncture_cli_core::StatusResponse;
+
+use crate::AppState;
+
+/// Routes for container orchestrators that are served without authentication
+pub fn router(app_state: AppState) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .with_state(app_state)
+}
+
+/// Responds with 200 if the daemon is alive and 503 otherwise
+async fn healthz(State(state): State<AppState>) -> (StatusCode, Json<StatusResponse>) {
+    let status = status(&state).await;
+
+    match status.healthy {
+        true => (StatusCode::OK, Json(status)),
+        false => (StatusCode::SERVICE_UNAVAILABLE, Json(status)),
+    }
+}
+
+/// Responds with 200 if the daemon can serve payments and 503 otherwise
+async fn readyz(State(state): State<AppState>) -> (StatusCode, Json<StatusResponse>) {
+    let status = status(&state).await;
+
+    match status.ready {
+        true => (StatusCode::OK, Json(status)),
+        false => (StatusCode::SERVICE_UNAVAILABLE, Json(status)),
+    }
+}
+
+pub async fn status(state: &AppState) -> StatusResponse {
+    let node_status = state.node.status();
+
+    let usable_channels = state
+        .node
+        .list_channels()
+        .into_iter()
+        .filter(|c| c.is_usable)
+        .count();
+
+    let database_reachable = database_reachable(state).await;
+
+    let home_relay = state
+        .endpoint
+        .home_relay()
+        .get()
+        .first()
+        .map(|relay_url| relay_url.to_string());
+
+    let healthy = node_status.is_running && database_reachable;
+
+    let synced = node_status.latest_onchain_wallet_sync_timestamp.is_some()
+        && node_status.latest_lightning_wallet_sync_timestamp.is_some();
+
+    StatusResponse {
+        healthy,
+        ready: healthy && synced && usable_channels > 0 && home_relay.is_some(),
+        node_running: node_status.is_running,
+        block_height: node_status.current_best_block.height,
+        block_hash: node_status.current_best_block.block_hash.to_string(),
+        latest_onchain_wallet_sync: node_status.latest_onchain_wallet_sync_timestamp,
+        latest_lightning_wallet_sync: node_status.latest_lightning_wallet_sync_timestamp,
+        usable_channels,
+        database_reachable,
+        home_relay,
+    }
+}
+
+/// A long running query such as a backup holds the connection, so we only
+/// consider the database unreachable if it does not respond within a timeout
+async fn database_reachable(state: &AppState) -> bool {
+    let Ok(mut conn) =
+        tokio::time::timeout(Duration::from_secs(10), state.db.get_connection()).await
+    else {
+        return false;
+    };
+
+    diesel::sql_query("SELECT 1").execute(&mut *conn).is_ok()
+}
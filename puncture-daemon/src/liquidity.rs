@@ -0,0 +1,335 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result, ensure};
+use bitcoin::hex::DisplayHex;
+use clap::ValueEnum;
+use rand::Rng;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use puncture_cli_core::{CreateChannelOrderRequest, OrderPaymentMethod};
+use puncture_core::unix_time;
+use puncture_daemon_db::models::LiquidityDecisionRecord;
+
+use crate::AppState;
+
+/// Fee reserve in sats we keep in the on-chain wallet when funding channels
+const ONCHAIN_FEE_RESERVE_SATS: u64 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LiquidityMode {
+    /// Do not check our liquidity
+    Off,
+    /// Log the decisions without acting on them
+    DryRun,
+    /// Buy and open channels as decided
+    Auto,
+}
+
+/// Our liquidity at the time of a check
+#[derive(Debug, Clone, Copy)]
+struct Snapshot {
+    inbound_capacity_msat: u64,
+    outbound_capacity_msat: u64,
+    liabilities_msat: u64,
+    onchain_balance_sats: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Action {
+    /// Open a channel to our outbound peer from on-chain funds
+    OpenOutbound,
+    /// Buy an inbound channel from the LSPS1 liquidity source
+    BuyInbound,
+    /// Neither action is possible, the operator has to step in
+    Alert,
+}
+
+impl Action {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Action::OpenOutbound => "open_outbound",
+            Action::BuyInbound => "buy_inbound",
+            Action::Alert => "alert",
+        }
+    }
+}
+
+/// The result of trying to buy an inbound channel
+enum Purchase {
+    /// The order has been paid, with a description of the order
+    Paid(String),
+    /// The quote was refused, with the reason for the refusal
+    Refused(String),
+}
+
+pub async fn run_liquidity(app_state: AppState, ct: CancellationToken) {
+    if app_state.args.liquidity_mode == LiquidityMode::Off {
+        return;
+    }
+
+    let mut interval =
+        tokio::time::interval(Duration::from_secs(app_state.args.liquidity_interval_secs));
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                if let Err(e) = check(&app_state).await {
+                    warn!(?e, "Failed to check liquidity");
+                }
+            }
+            _ = ct.cancelled() => {
+                break;
+            }
+        }
+    }
+}
+
+/// Compare our capacity against the user liabilities and the configured
+/// thresholds and act on the result unless we are in dry-run mode. Returns
+/// the decision if any action was required.
+pub async fn check(state: &AppState) -> Result<Option<LiquidityDecisionRecord>> {
+    // Channels we already opened or bought take a while to confirm, we wait
+    // for them instead of acquiring the same liquidity twice
+    if has_pending_channels(state).await {
+        info!("Skipping liquidity check while channels are pending");
+
+        return Ok(None);
+    }
+
+    let snapshot = snapshot(state).await;
+
+    let Some((action, reason)) = decide(state, &snapshot) else {
+        return Ok(None);
+    };
+
+    let dry_run = state.args.liquidity_mode != LiquidityMode::Auto;
+
+    let (action, reason, outcome) = match (dry_run, action) {
+        (true, action) => (action, reason, None),
+        (false, Action::OpenOutbound) => (
+            Action::OpenOutbound,
+            reason,
+            Some(open_outbound(state).await),
+        ),
+        (false, Action::BuyInbound) => match buy_inbound(state).await {
+            Ok(Purchase::Paid(outcome)) => (Action::BuyInbound, reason, Some(Ok(outcome))),
+            Ok(Purchase::Refused(refusal)) => {
+                (Action::Alert, format!("{reason}, but {refusal}"), None)
+            }
+            Err(e) => (Action::BuyInbound, reason, Some(Err(e))),
+        },
+        (false, Action::Alert) => (Action::Alert, reason, None),
+    };
+
+    let outcome = outcome.map(|outcome| match outcome {
+        Ok(outcome) => outcome,
+        Err(e) => format!("Failed: {e:#}"),
+    });
+
+    if action == Action::Alert {
+        warn!(?snapshot, reason, "Liquidity requires operator attention");
+    }
+
+    let record = LiquidityDecisionRecord {
+        id: rand::rng().random::<[u8; 16]>().as_hex().to_string(),
+        action: action.as_str().to_string(),
+        reason,
+        dry_run,
+        outcome,
+        inbound_capacity_msat: snapshot.inbound_capacity_msat as i64,
+        outbound_capacity_msat: snapshot.outbound_capacity_msat as i64,
+        liabilities_msat: snapshot.liabilities_msat as i64,
+        onchain_balance_sats: snapshot.onchain_balance_sats as i64,
+        created_at: unix_time(),
+    };
+
+    let mut conn = state.db.get_connection().await;
+
+    // A decision we did not act on repeats every interval until the liquidity
+    // or the thresholds change, we only record it once
+    let repeated = record.outcome.is_none()
+        && crate::db::list_liquidity_decisions(&mut conn, 1)
+            .await
+            .first()
+            .is_some_and(|latest| {
+                latest.action == record.action
+                    && latest.reason == record.reason
+                    && latest.dry_run == record.dry_run
+            });
+
+    if !repeated {
+        crate::db::create_liquidity_decision(&mut conn, record.clone()).await;
+    }
+
+    Ok(Some(record))
+}
+
+async fn snapshot(state: &AppState) -> Snapshot {
+    let channels = state.node.list_channels();
+
+    let usable = channels.iter().filter(|channel| channel.is_usable);
+
+    let liabilities_msat = crate::db::list_user_balances(&mut *state.db.get_connection().await)
+        .await
+        .into_iter()
+        .map(|(_, balance_msat)| balance_msat)
+        .sum();
+
+    Snapshot {
+        inbound_capacity_msat: usable
+            .clone()
+            .map(|channel| channel.inbound_capacity_msat)
+            .sum(),
+        outbound_capacity_msat: usable.map(|channel| channel.outbound_capacity_msat).sum(),
+        liabilities_msat,
+        onchain_balance_sats: state.node.list_balances().spendable_onchain_balance_sats,
+    }
+}
+
+async fn has_pending_channels(state: &AppState) -> bool {
+    if state
+        .node
+        .list_channels()
+        .iter()
+        .any(|channel| !channel.is_channel_ready)
+    {
+        return true;
+    }
+
+    crate::db::list_channel_orders(&mut *state.db.get_connection().await)
+        .await
+        .iter()
+        .any(|order| {
+            order.payment.is_some()
+                && order.funding_outpoint.is_none()
+                && order.payment_state != "refunded"
+        })
+}
+
+/// Users have to be able to withdraw their balances, so a lack of outbound
+/// capacity takes priority over a lack of inbound capacity
+fn decide(state: &AppState, snapshot: &Snapshot) -> Option<(Action, String)> {
+    let args = &state.args;
+
+    let required_outbound_msat =
+        snapshot.liabilities_msat * args.liquidity_min_outbound_percent / 100;
+
+    let channel_sats = args.liquidity_channel_sats;
+
+    if snapshot.outbound_capacity_msat < required_outbound_msat {
+        let reason = format!(
+            "Outbound capacity of {} sats is below {}% of the user liabilities of {} sats",
+            snapshot.outbound_capacity_msat / 1000,
+            args.liquidity_min_outbound_percent,
+            snapshot.liabilities_msat / 1000,
+        );
+
+        if snapshot.onchain_balance_sats < channel_sats + ONCHAIN_FEE_RESERVE_SATS {
+            return Some((
+                Action::Alert,
+                format!("{reason}, but the on-chain balance is too low to open a channel"),
+            ));
+        }
+
+        return Some((Action::OpenOutbound, reason));
+    }
+
+    if snapshot.inbound_capacity_msat < args.liquidity_min_inbound_sats * 1000 {
+        let reason = format!(
+            "Inbound capacity of {} sats is below the minimum of {} sats",
+            snapshot.inbound_capacity_msat / 1000,
+            args.liquidity_min_inbound_sats,
+        );
+
+        if args.lsp1_node_id.is_none() {
+            return Some((
+                Action::Alert,
+                format!("{reason}, but no LSPS1 liquidity source is configured"),
+            ));
+        }
+
+        return Some((Action::BuyInbound, reason));
+    }
+
+    None
+}
+
+/// Open an unannounced channel to the configured outbound peer and fall back
+/// to the LSPS1 liquidity source
+async fn open_outbound(state: &AppState) -> Result<String> {
+    let (node_id, address) = match &state.args.liquidity_outbound_peer {
+        Some(peer) => (peer.node_id, peer.address.clone()),
+        None => (
+            state
+                .args
+                .lsp1_node_id
+                .context("No outbound peer configured, set LIQUIDITY_OUTBOUND_PEER")?,
+            state
+                .args
+                .lsp1_socket_address
+                .as_deref()
+                .context("No outbound peer configured, set LIQUIDITY_OUTBOUND_PEER")?
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Invalid LSP1 socket address: {e:?}"))?,
+        ),
+    };
+
+    let channel_id = state
+        .node
+        .open_channel(
+            node_id,
+            address,
+            state.args.liquidity_channel_sats,
+            None,
+            None,
+        )
+        .map_err(|e| anyhow::anyhow!("Failed to open channel: {e}"))?;
+
+    info!(?node_id, ?channel_id, "Opened outbound channel");
+
+    Ok(format!("Opened channel {}", channel_id.0))
+}
+
+/// Buy an inbound channel and pay for it from on-chain funds, unless the
+/// liquidity source quotes a fee above our maximum
+async fn buy_inbound(state: &AppState) -> Result<Purchase> {
+    let order = crate::lsp::create_order(
+        state,
+        &CreateChannelOrderRequest {
+            lsp_balance_sat: state.args.liquidity_channel_sats,
+            client_balance_sat: 0,
+            channel_expiry_blocks: 13140,
+            public: false,
+        },
+    )
+    .await?;
+
+    if order.fee_total_sat > state.args.liquidity_max_fee_sats {
+        warn!(?order.order_id, ?order.fee_total_sat, "Refusing overpriced channel order");
+
+        return Ok(Purchase::Refused(format!(
+            "the liquidity source quoted a fee of {} sats above the maximum of {} sats",
+            order.fee_total_sat, state.args.liquidity_max_fee_sats
+        )));
+    }
+
+    let spendable_sats = state.node.list_balances().spendable_onchain_balance_sats;
+
+    ensure!(
+        order.order_total_sat + ONCHAIN_FEE_RESERVE_SATS <= spendable_sats,
+        "The order {} costs {} sats but the on-chain balance is only {} sats",
+        order.order_id,
+        order.order_total_sat,
+        spendable_sats
+    );
+
+    let order = crate::lsp::pay_order(state, &order.order_id, OrderPaymentMethod::Onchain).await?;
+
+    info!(?order.order_id, ?order.fee_total_sat, "Bought inbound channel");
+
+    Ok(Purchase::Paid(format!(
+        "Paid order {} with a fee of {} sats",
+        order.order_id, order.fee_total_sat
+    )))
+}
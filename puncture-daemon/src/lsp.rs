@@ -0,0 +1,429 @@
+use std::str::FromStr;
+
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::hashes::Hash;
+use bitcoin::hex::DisplayHex;
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::{Address, Network};
+use ldk_node::lightning::ln::channelmanager::PaymentId;
+use ldk_node::lightning_liquidity::lsps1::msgs::{OrderId, PaymentState};
+use ldk_node::liquidity::LSPS1OrderStatus;
+use ldk_node::payment::PaymentKind;
+use ldk_node::{Node, NodeError};
+use lightning::ln::msgs::SocketAddress;
+use lightning_invoice::{Bolt11Invoice, Bolt11InvoiceDescription};
+use serde_json::Value;
+use tracing::info;
+use url::Url;
+
+use puncture_cli_core::{ChannelOrderInfo, CreateChannelOrderRequest, OrderPaymentMethod};
+use puncture_core::unix_time;
+use puncture_daemon_db::models::ChannelOrderRecord;
+
+use crate::{AppState, Args};
+
+/// Public nodes that support Bolt12 we connect to on first startup on mainnet
+const MAINNET_BOOTSTRAP_PEERS: [&str; 3] = [
+    // Acinq
+    "03864ef025fde8fb587d989186ce6a4a186895ee44a926bfc370e2c366597a3f8f@3.33.236.230:9735",
+    // Block
+    "027100442c3b79f606f80f322d98d499eefcb060599efc5d4ecb00209c2cb54190@3.230.33.224:9735",
+    // Strike
+    "03c8e5f583585cac1de2b7503a6ccd3c12ba477cfd139cd4905be504c2f48e86bd@34.73.189.183:9735",
+];
+
+/// LSPs we request channels from on mainnet unless configured otherwise
+const MAINNET_LSPS1_PROVIDERS: [&str; 1] = [
+    "megalith|038a9e56512ec98da2b5789761f7af8f280baf98a09282360cd6ff1381b5e889bf@64.23.162.51:9735|https://megalithic.me/api/lsps1/v1/create_order",
+];
+
+/// A lightning node given as NODE_ID@HOST:PORT
+#[derive(Debug, Clone)]
+pub struct Peer {
+    pub node_id: PublicKey,
+    pub address: SocketAddress,
+}
+
+impl FromStr for Peer {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (node_id, address) = s
+            .split_once('@')
+            .context("Expected peer as NODE_ID@HOST:PORT")?;
+
+        Ok(Self {
+            node_id: node_id.parse().context("Invalid peer node id")?,
+            address: SocketAddress::from_str(address)
+                .map_err(|e| anyhow!("Invalid peer address: {e:?}"))?,
+        })
+    }
+}
+
+/// An LSP selling channels via the LSPS1 HTTP API given as NAME|NODE_ID@HOST:PORT|URL
+#[derive(Debug, Clone)]
+pub struct Lsps1Provider {
+    pub name: String,
+    pub peer: Peer,
+    /// The create_order endpoint of the provider's API
+    pub url: Url,
+}
+
+impl FromStr for Lsps1Provider {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let [name, peer, url] = s
+            .split('|')
+            .collect::<Vec<&str>>()
+            .try_into()
+            .map_err(|_| anyhow!("Expected provider as NAME|NODE_ID@HOST:PORT|URL"))?;
+
+        ensure!(!name.is_empty(), "Provider name is empty");
+
+        Ok(Self {
+            name: name.to_string(),
+            peer: peer.parse()?,
+            url: url.parse().context("Invalid provider url")?,
+        })
+    }
+}
+
+/// The configured bootstrap peers, defaulting to well connected public nodes on mainnet
+pub fn bootstrap_peers(args: &Args) -> Vec<Peer> {
+    match &args.bootstrap_peers {
+        Some(peers) => peers.clone(),
+        None if args.bitcoin_network == Network::Bitcoin => MAINNET_BOOTSTRAP_PEERS
+            .iter()
+            .map(|peer| peer.parse().expect("Default peer is valid"))
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// The configured LSPS1 providers, defaulting to Megalith on mainnet
+pub fn lsps1_providers(args: &Args) -> Vec<Lsps1Provider> {
+    match &args.lsps1_providers {
+        Some(providers) => providers.clone(),
+        None if args.bitcoin_network == Network::Bitcoin => MAINNET_LSPS1_PROVIDERS
+            .iter()
+            .map(|provider| provider.parse().expect("Default provider is valid"))
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Find a provider by name or return the first one if no name is given
+pub fn lsps1_provider(args: &Args, name: Option<&str>) -> Result<Lsps1Provider> {
+    let providers = lsps1_providers(args);
+
+    match name {
+        Some(name) => providers
+            .into_iter()
+            .find(|provider| provider.name == name)
+            .with_context(|| format!("Unknown LSPS1 provider {name}")),
+        None => providers
+            .into_iter()
+            .next()
+            .context("No LSPS1 provider configured"),
+    }
+}
+
+/// Order a channel from the provider and return the invoice to pay for it
+pub async fn request_channel(
+    state: &AppState,
+    provider: &Lsps1Provider,
+    lsp_balance_sat: u64,
+    client_balance_sat: u64,
+    channel_expiry_blocks: u32,
+    public: bool,
+) -> Result<String> {
+    state
+        .node
+        .connect(provider.peer.node_id, provider.peer.address.clone(), true)
+        .map_err(|e| anyhow!("Failed to connect to {} LSP node: {e}", provider.name))?;
+
+    info!(provider = provider.name, "Ensured connection to LSP node");
+
+    let payload = serde_json::json!({
+        "lsp_balance_sat": lsp_balance_sat.to_string(),
+        "client_balance_sat": client_balance_sat.to_string(),
+        "required_channel_confirmations": 0,
+        "funding_confirms_within_blocks": 6,
+        "channel_expiry_blocks": channel_expiry_blocks,
+        "token": "",
+        "refund_on_chain_address": null,
+        "announce_channel": public,
+        "public_key": state.node.node_id().to_string()
+    });
+
+    let response = reqwest::Client::new()
+        .post(provider.url.clone())
+        .json(&payload)
+        .send()
+        .await
+        .with_context(|| format!("Failed to call {} API", provider.name))?;
+
+    if !response.status().is_success() {
+        bail!(
+            "{} API error: {}",
+            provider.name,
+            response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string())
+        );
+    }
+
+    let response: Value = response
+        .json()
+        .await
+        .with_context(|| format!("Failed to parse {} response", provider.name))?;
+
+    let invoice = response
+        .get("payment")
+        .and_then(|v| v.get("bolt11"))
+        .and_then(|v| v.get("invoice"))
+        .and_then(|v| v.as_str())
+        .with_context(|| format!("Missing invoice in {} response", provider.name))?;
+
+    Ok(invoice.to_string())
+}
+
+/// Order an inbound channel from the LSPS1 liquidity source over our lightning
+/// connection to it and store the quote until we decide to pay it
+pub async fn create_order(
+    state: &AppState,
+    request: &CreateChannelOrderRequest,
+) -> Result<ChannelOrderInfo> {
+    let lsp_node_id = state.args.lsp1_node_id.context(
+        "No LSPS1 liquidity source configured, set LSP1_NODE_ID and LSP1_SOCKET_ADDRESS",
+    )?;
+
+    let status = state
+        .node
+        .lsps1_liquidity()
+        .request_channel(
+            request.lsp_balance_sat,
+            request.client_balance_sat,
+            request.channel_expiry_blocks,
+            request.public,
+        )
+        .map_err(liquidity_error)?;
+
+    let bolt11 = status.payment_options.bolt11.as_ref();
+
+    let onchain = status.payment_options.onchain.as_ref();
+
+    let (fee_total_sat, order_total_sat) = match (bolt11, onchain) {
+        (Some(bolt11), _) => (bolt11.fee_total_sat, bolt11.order_total_sat),
+        (None, Some(onchain)) => (onchain.fee_total_sat, onchain.order_total_sat),
+        (None, None) => bail!("The LSP did not offer any payment option"),
+    };
+
+    let record = ChannelOrderRecord {
+        id: status.order_id.0.clone(),
+        lsp_node_id: lsp_node_id.to_string(),
+        lsp_balance_sat: status.order_params.lsp_balance_sat as i64,
+        client_balance_sat: status.order_params.client_balance_sat as i64,
+        channel_expiry_blocks: status.order_params.channel_expiry_blocks as i32,
+        announce_channel: status.order_params.announce_channel,
+        fee_total_sat: fee_total_sat as i64,
+        order_total_sat: order_total_sat as i64,
+        invoice: bolt11.map(|bolt11| bolt11.invoice.to_string()),
+        onchain_address: onchain.map(|onchain| onchain.address.to_string()),
+        payment_state: payment_state(&status).to_string(),
+        payment: None,
+        funding_outpoint: None,
+        created_at: unix_time(),
+        updated_at: unix_time(),
+    };
+
+    crate::db::create_channel_order(&mut *state.db.get_connection().await, record.clone()).await;
+
+    Ok(crate::db::channel_order_info(record))
+}
+
+/// Pay a quoted order from our channels or our on-chain wallet
+pub async fn pay_order(
+    state: &AppState,
+    order_id: &str,
+    method: OrderPaymentMethod,
+) -> Result<ChannelOrderInfo> {
+    let mut conn = state.db.get_connection().await;
+
+    let record = crate::db::get_channel_order(&mut conn, order_id)
+        .await
+        .context("Unknown channel order")?;
+
+    ensure!(record.payment.is_none(), "The order has already been paid");
+
+    ensure!(
+        record.payment_state == "expect_payment",
+        "The order is {} and can no longer be paid",
+        record.payment_state
+    );
+
+    let payment = match method {
+        OrderPaymentMethod::Lightning => {
+            let invoice = record
+                .invoice
+                .as_deref()
+                .context("The LSP does not accept lightning payments for this order")?
+                .parse::<Bolt11Invoice>()
+                .map_err(|e| anyhow!("Invalid order invoice: {e}"))?;
+
+            state
+                .node
+                .bolt11_payment()
+                .send(&invoice, None)
+                .map_err(|e| anyhow!("Failed to pay order invoice: {e}"))?
+                .0
+                .as_hex()
+                .to_string()
+        }
+        OrderPaymentMethod::Onchain => {
+            let address = record
+                .onchain_address
+                .as_deref()
+                .context("The LSP does not accept on-chain payments for this order")?
+                .parse::<Address<NetworkUnchecked>>()
+                .context("Invalid order address")?
+                .require_network(state.node.config().network)
+                .context("Order address is for another network")?;
+
+            state
+                .node
+                .onchain_payment()
+                .send_to_address(&address, record.order_total_sat as u64, None)
+                .map_err(|e| anyhow!("Failed to pay order address: {e}"))?
+                .to_string()
+        }
+    };
+
+    crate::db::set_channel_order_payment(&mut conn, order_id, payment).await;
+
+    info!(?order_id, ?method, "Paid channel order");
+
+    let record = crate::db::get_channel_order(&mut conn, order_id)
+        .await
+        .expect("Order exists");
+
+    Ok(crate::db::channel_order_info(record))
+}
+
+/// Fetch the current state of an order from the LSP
+pub async fn refresh_order(state: &AppState, order_id: &str) -> Result<ChannelOrderInfo> {
+    ensure!(
+        crate::db::get_channel_order(&mut *state.db.get_connection().await, order_id)
+            .await
+            .is_some(),
+        "Unknown channel order"
+    );
+
+    let status = state
+        .node
+        .lsps1_liquidity()
+        .check_order_status(OrderId(order_id.to_string()))
+        .map_err(liquidity_error)?;
+
+    let mut conn = state.db.get_connection().await;
+
+    crate::db::update_channel_order(
+        &mut conn,
+        order_id,
+        payment_state(&status),
+        status
+            .channel_state
+            .map(|channel| channel.funding_outpoint.to_string()),
+    )
+    .await;
+
+    let record = crate::db::get_channel_order(&mut conn, order_id)
+        .await
+        .expect("Order exists");
+
+    Ok(crate::db::channel_order_info(record))
+}
+
+/// The largest amount we can currently receive over our usable channels. The
+/// inbound capacity of a channel already excludes the channel reserve and the
+/// amounts locked in pending HTLCs.
+pub fn receivable_max_msat(node: &Node) -> u64 {
+    node.list_channels()
+        .iter()
+        .filter(|channel| channel.is_usable)
+        .map(|channel| channel.inbound_capacity_msat)
+        .sum()
+}
+
+/// Create an invoice the LSPS2 liquidity source opens a just-in-time channel
+/// for once it is paid and return it with the opening fee the LSP deducts
+pub fn receive_via_jit_channel(
+    node: &Node,
+    amount_msat: u64,
+    description: &Bolt11InvoiceDescription,
+    expiry_secs: u32,
+    max_lsp_fee_msat: u64,
+) -> Result<(Bolt11Invoice, u64)> {
+    let invoice = node
+        .bolt11_payment()
+        .receive_via_jit_channel(
+            amount_msat,
+            description,
+            expiry_secs,
+            Some(max_lsp_fee_msat),
+        )
+        .map_err(|error| match error {
+            NodeError::LiquiditySourceUnavailable => anyhow!("Insufficient inbound liquidity"),
+            NodeError::LiquidityFeeTooHigh => {
+                anyhow!("The channel opening fee exceeds the maximum fee")
+            }
+            error => anyhow!("LSPS2 request failed: {error}"),
+        })?;
+
+    // The fee of the cheapest offer the LSP made is stored as the fee limit
+    let lsp_fee_msat = match node
+        .payment(&PaymentId(invoice.payment_hash().to_byte_array()))
+        .context("JIT payment not found")?
+        .kind
+    {
+        PaymentKind::Bolt11Jit { lsp_fee_limits, .. } => lsp_fee_limits
+            .max_total_opening_fee_msat
+            .context("JIT payment is missing its opening fee")?,
+        _ => bail!("Unexpected payment kind"),
+    };
+
+    info!(?amount_msat, ?lsp_fee_msat, "Created JIT channel invoice");
+
+    Ok((invoice, lsp_fee_msat))
+}
+
+/// The LSP tracks the state of every payment option, the order is paid or
+/// refunded once any of them is
+fn payment_state(status: &LSPS1OrderStatus) -> &'static str {
+    let states = [
+        status.payment_options.bolt11.as_ref().map(|p| &p.state),
+        status.payment_options.onchain.as_ref().map(|p| &p.state),
+    ];
+
+    if states.contains(&Some(&PaymentState::Paid)) {
+        return "paid";
+    }
+
+    if states.contains(&Some(&PaymentState::Refunded)) {
+        return "refunded";
+    }
+
+    "expect_payment"
+}
+
+fn liquidity_error(error: NodeError) -> anyhow::Error {
+    match error {
+        NodeError::LiquiditySourceUnavailable => anyhow!(
+            "No LSPS1 liquidity source configured, set LSP1_NODE_ID and LSP1_SOCKET_ADDRESS"
+        ),
+        error => anyhow!("LSPS1 request failed: {error}"),
+    }
+}
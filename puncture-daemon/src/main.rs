@@ -1,8 +1,17 @@
+mod archive;
+mod backup;
 mod cli;
 mod client;
+mod config;
 mod convert;
 mod db;
 mod events;
+mod forwarding;
+mod health;
+mod liquidity;
+mod lsp;
+mod metrics;
+mod solvency;
 mod ui;
 
 use std::fs;
@@ -12,24 +21,36 @@ use std::str::FromStr;
 use std::sync::Arc;
 
 use anyhow::{Context, Result, bail};
+use bitcoin::hex::DisplayHex;
 use bitcoin::secp256k1::PublicKey;
 use clap::{ArgGroup, Parser};
+use dashmap::DashMap;
 use iroh::Endpoint;
 use ldk_node::bitcoin::Network;
 use ldk_node::payment::PaymentKind;
 use ldk_node::{Builder, Event, Node};
 use lightning::ln::msgs::SocketAddress;
+use rand::Rng;
+use tokio::sync::RwLock;
 use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
 use tracing_subscriber::EnvFilter;
 use url::Url;
 
+use puncture_cli_core::{ADMIN_ALPN, Settings};
 use puncture_core::db::Database;
 use puncture_core::{secret, unix_time};
+use puncture_daemon_db::models::ForwardRecord;
 
 use crate::{
+    cli::auth::{CliTokens, read_or_generate_token},
     convert::{IntoPayment, IntoReceiveRecord},
     events::EventBus,
+    liquidity::LiquidityMode,
+    lsp::{Lsps1Provider, Peer},
+    metrics::Metrics,
+    solvency::LiabilityTree,
+    ui::auth::UiSession,
 };
 
 #[derive(Parser, Debug, Clone)]
@@ -43,6 +64,11 @@ use crate::{
         .required(false)
         .multiple(false)
         .requires_all(["lsp1_node_id", "lsp1_socket_address"])
+), group(
+    ArgGroup::new("lsp2_config")
+        .required(false)
+        .multiple(false)
+        .requires_all(["lsp2_node_id", "lsp2_socket_address"])
 ))]
 struct Args {
     /// Directory path for storing user account data in a SQLite database.
@@ -65,37 +91,105 @@ struct Args {
     #[arg(long, env = "ESPLORA_RPC_URL")]
     esplora_rpc_url: Option<Url>,
 
+    /// Passphrase to encrypt the daemon's secret key at rest. An existing unencrypted key is encrypted on startup.
+    #[arg(long, env = "PUNCTURE_SECRET_PASSPHRASE", hide_env_values = true)]
+    secret_passphrase: Option<String>,
+
+    /// File containing the passphrase to encrypt the daemon's secret key at rest, e.g. a docker secret.
+    #[arg(
+        long,
+        env = "PUNCTURE_SECRET_PASSPHRASE_FILE",
+        conflicts_with = "secret_passphrase"
+    )]
+    secret_passphrase_file: Option<PathBuf>,
+
+    /// Comma separated node ids of puncture-cli instances allowed to manage this daemon over iroh.
+    #[arg(long, env = "ADMIN_NODE_IDS", value_delimiter = ',')]
+    admin_node_ids: Vec<iroh::NodeId>,
+
     /// Name of the puncture instance as displayed to the user
     #[arg(long, env = "DAEMON_NAME")]
     daemon_name: String,
 
-    /// Liquidity source LSPs1 node ID
-    #[arg(long, env = "LSP1_NODE_ID", hide = true)]
+    /// Comma separated peers as NODE_ID@HOST:PORT to connect to on first startup. Defaults to public Bolt12 nodes on mainnet.
+    #[arg(long, env = "BOOTSTRAP_PEERS", value_delimiter = ',')]
+    bootstrap_peers: Option<Vec<Peer>>,
+
+    /// Comma separated LSPs to request channels from as NAME|NODE_ID@HOST:PORT|URL. Defaults to Megalith on mainnet.
+    #[arg(long, env = "LSPS1_PROVIDERS", value_delimiter = ',')]
+    lsps1_providers: Option<Vec<Lsps1Provider>>,
+
+    /// Node id of the LSP to buy channels from via the LSPS1 protocol over lightning.
+    #[arg(long, env = "LSP1_NODE_ID")]
     lsp1_node_id: Option<PublicKey>,
 
-    /// Liquidity source LSPs1 node address (IP:PORT, HOSTNAME:PORT or Onion address)
-    #[arg(long, env = "LSP1_SOCKET_ADDRESS", hide = true)]
+    /// Address of the LSPS1 liquidity source (IP:PORT, HOSTNAME:PORT or Onion address).
+    #[arg(long, env = "LSP1_SOCKET_ADDRESS")]
     lsp1_socket_address: Option<String>,
 
-    /// Liquidity source LSPs1 node token
-    #[arg(long, env = "LSP1_TOKEN", hide = true)]
+    /// Token the LSPS1 liquidity source requires to accept orders, if any.
+    #[arg(long, env = "LSP1_TOKEN", hide_env_values = true)]
     lsp1_token: Option<String>,
 
-    /// Fee rate in parts per million (PPM) applied to outgoing Lightning payments.
-    #[arg(long, env = "FEE_PPM", default_value = "5000")]
-    fee_ppm: u64,
+    /// Node id of the LSP opening just-in-time channels via the LSPS2 protocol when users receive without inbound liquidity.
+    #[arg(long, env = "LSP2_NODE_ID")]
+    lsp2_node_id: Option<PublicKey>,
+
+    /// Address of the LSPS2 liquidity source (IP:PORT, HOSTNAME:PORT or Onion address).
+    #[arg(long, env = "LSP2_SOCKET_ADDRESS")]
+    lsp2_socket_address: Option<String>,
+
+    /// Token the LSPS2 liquidity source requires to open channels, if any.
+    #[arg(long, env = "LSP2_TOKEN", hide_env_values = true)]
+    lsp2_token: Option<String>,
+
+    /// Whether to periodically check our channel capacity against the user liabilities and thresholds below and act on it.
+    #[arg(long, env = "LIQUIDITY_MODE", value_enum, default_value = "off")]
+    liquidity_mode: LiquidityMode,
+
+    /// Interval in seconds between liquidity checks.
+    #[arg(long, env = "LIQUIDITY_INTERVAL_SECS", default_value = "3600")]
+    liquidity_interval_secs: u64,
+
+    /// Minimum inbound capacity in satoshis, below it we buy a channel from the LSPS1 liquidity source.
+    #[arg(long, env = "LIQUIDITY_MIN_INBOUND_SATS", default_value = "1000000")]
+    liquidity_min_inbound_sats: u64,
+
+    /// Minimum outbound capacity as percentage of the user liabilities, below it we open a channel from on-chain funds.
+    #[arg(long, env = "LIQUIDITY_MIN_OUTBOUND_PERCENT", default_value = "100")]
+    liquidity_min_outbound_percent: u64,
+
+    /// Size in satoshis of the channels opened or bought by the liquidity manager.
+    #[arg(long, env = "LIQUIDITY_CHANNEL_SATS", default_value = "1000000")]
+    liquidity_channel_sats: u64,
+
+    /// Maximum fee in satoshis we pay the LSPS1 liquidity source for a channel, more expensive quotes are refused.
+    #[arg(long, env = "LIQUIDITY_MAX_FEE_SATS", default_value = "20000")]
+    liquidity_max_fee_sats: u64,
+
+    /// Peer as NODE_ID@HOST:PORT to open outbound channels to. Defaults to the LSPS1 liquidity source.
+    #[arg(long, env = "LIQUIDITY_OUTBOUND_PEER")]
+    liquidity_outbound_peer: Option<Peer>,
+
+    /// TOML file with fee and limit settings that is reloaded on SIGHUP. Env vars take precedence over it.
+    #[arg(long, env = "CONFIG_FILE")]
+    config_file: Option<PathBuf>,
+
+    /// Fee rate in parts per million (PPM) applied to outgoing Lightning payments. Defaults to 5000.
+    #[arg(long, env = "FEE_PPM")]
+    fee_ppm: Option<u64>,
 
-    /// Fixed base fee in millisatoshis added to all outgoing Lightning payments.
-    #[arg(long, env = "BASE_FEE_MSAT", default_value = "10000")]
-    base_fee_msat: u64,
+    /// Fixed base fee in millisatoshis added to all outgoing Lightning payments. Defaults to 10000.
+    #[arg(long, env = "BASE_FEE_MSAT")]
+    base_fee_msat: Option<u64>,
 
-    /// Expiration time in seconds for all generated Lightning invoices.
-    #[arg(long, env = "INVOICE_EXPIRY_SECS", default_value = "3600")]
-    invoice_expiry_secs: u32,
+    /// Expiration time in seconds for all generated Lightning invoices. Defaults to 3600.
+    #[arg(long, env = "INVOICE_EXPIRY_SECS")]
+    invoice_expiry_secs: Option<u32>,
 
-    /// Fixed base fee in millisatoshis added to all outgoing on-chain payments.
-    #[arg(long, env = "ONCHAIN_BASE_FEE_MSAT", default_value = "5000000")]
-    onchain_base_fee_msat: u64,
+    /// Fixed base fee in millisatoshis added to all outgoing on-chain payments. Defaults to 5000000.
+    #[arg(long, env = "ONCHAIN_BASE_FEE_MSAT")]
+    onchain_base_fee_msat: Option<u64>,
 
     /// Network address and port for the client interface to be served on.
     #[arg(long, env = "CLIENT_BIND", default_value = "0.0.0.0:8080")]
@@ -113,17 +207,41 @@ struct Args {
     #[arg(long, env = "UI_BIND", default_value = "0.0.0.0:8083")]
     ui_bind: SocketAddr,
 
-    /// Minimum amount in satoshis enforced across all incoming and outgoing payments.
-    #[arg(long, env = "MIN_AMOUNT_SATS", default_value = "1")]
-    min_amount_sats: u32,
+    /// Password for the UI interface. Defaults to a password generated into ui.password in the data directory.
+    #[arg(long, env = "UI_PASSWORD", hide_env_values = true)]
+    ui_password: Option<String>,
 
-    /// Maximum amount in satoshis enforced across all incoming and outgoing payments.
-    #[arg(long, env = "MAX_AMOUNT_SATS", default_value = "100000")]
-    max_amount_sats: u32,
+    /// Network address and port to serve Prometheus metrics on. Metrics are disabled if unset.
+    #[arg(long, env = "METRICS_BIND")]
+    metrics_bind: Option<SocketAddr>,
 
-    /// Maximum number of pending invoices and outgoing payments each user can have simultaneously.
-    #[arg(long, env = "MAX_PENDING_PAYMENTS_PER_USER", default_value = "10")]
-    max_pending_payments_per_user: u32,
+    /// Directory backups created via the CLI are written to. Defaults to backups in the data directory.
+    #[arg(long, env = "BACKUP_DIR")]
+    backup_dir: Option<PathBuf>,
+
+    /// Number of backups to keep, older backups are deleted when a new one is created.
+    #[arg(long, env = "BACKUP_RETENTION", default_value = "7")]
+    backup_retention: usize,
+
+    /// Validate and restore the given backup directory, then exit. Unset it before the next start.
+    #[arg(long, env = "RESTORE_BACKUP")]
+    restore_backup: Option<PathBuf>,
+
+    /// Also restore LDK's channel state from the backup. Channel state older than the one known to your peers lets them claim all channel funds.
+    #[arg(long, env = "I_UNDERSTAND_STALE_CHANNEL_STATE")]
+    i_understand_stale_channel_state: bool,
+
+    /// Minimum amount in satoshis enforced across all incoming and outgoing payments. Defaults to 1.
+    #[arg(long, env = "MIN_AMOUNT_SATS")]
+    min_amount_sats: Option<u32>,
+
+    /// Maximum amount in satoshis enforced across all incoming and outgoing payments. Defaults to 100000.
+    #[arg(long, env = "MAX_AMOUNT_SATS")]
+    max_amount_sats: Option<u32>,
+
+    /// Maximum number of pending invoices and outgoing payments each user can have simultaneously. Defaults to 10.
+    #[arg(long, env = "MAX_PENDING_PAYMENTS_PER_USER")]
+    max_pending_payments_per_user: Option<u32>,
 
     /// The log level for the puncture daemon
     #[arg(long, env = "LOG_LEVEL", default_value = "info")]
@@ -137,6 +255,27 @@ struct AppState {
     node: Arc<Node>,
     event_bus: EventBus,
     node_id: iroh::NodeId,
+    endpoint: Endpoint,
+    secret_key: iroh::SecretKey,
+    cli_tokens: CliTokens,
+    ui_password: String,
+    ui_sessions: Arc<DashMap<String, UiSession>>,
+    liability_tree: Arc<RwLock<Option<LiabilityTree>>>,
+    metrics: Metrics,
+    settings: Arc<RwLock<Settings>>,
+}
+
+/// Returns the passphrase the daemon's secret keys are encrypted with, if any
+fn secret_passphrase(args: &Args) -> Result<Option<String>> {
+    match &args.secret_passphrase_file {
+        Some(path) => Ok(Some(
+            fs::read_to_string(path)
+                .context("Failed to read secret passphrase file")?
+                .trim_end()
+                .to_string(),
+        )),
+        None => Ok(args.secret_passphrase.clone()),
+    }
 }
 
 async fn shutdown_signal() {
@@ -159,6 +298,18 @@ fn main() -> Result<()> {
 
     fs::create_dir_all(&args.ldk_data_dir).expect("Failed to create ldk data directory");
 
+    if let Some(backup_path) = &args.restore_backup {
+        backup::restore(&args, backup_path)?;
+
+        info!("Backup restored, unset RESTORE_BACKUP and restart the daemon");
+
+        return Ok(());
+    }
+
+    let settings = config::load(&args)?;
+
+    info!(?settings, "Loaded settings");
+
     let mut builder = Builder::new();
 
     builder.set_log_facade_logger();
@@ -204,6 +355,16 @@ fn main() -> Result<()> {
         );
     }
 
+    if let (Some(node_id), Some(socket_address)) = (args.lsp2_node_id, &args.lsp2_socket_address) {
+        builder.set_liquidity_source_lsps2(
+            node_id,
+            SocketAddress::from_str(socket_address)
+                .ok()
+                .context("Invalid LSP2 socket address")?,
+            args.lsp2_token.clone(),
+        );
+    }
+
     builder.set_listening_addresses(vec![args.ldk_bind.into()])?;
 
     let node = Arc::new(builder.build()?);
@@ -212,36 +373,12 @@ fn main() -> Result<()> {
 
     node.start_with_runtime(runtime.clone())?;
 
-    // On first startup, connect to public nodes that support Bolt12
-    if !secret::exists(&args.puncture_data_dir) && args.bitcoin_network == Network::Bitcoin {
-        if let Err(e) = node.connect(
-            "03864ef025fde8fb587d989186ce6a4a186895ee44a926bfc370e2c366597a3f8f"
-                .parse()
-                .unwrap(),
-            "3.33.236.230:9735".parse().unwrap(),
-            true,
-        ) {
-            warn!(?e, "Failed to connect to Acinq's public node");
-        }
-
-        if let Err(e) = node.connect(
-            "027100442c3b79f606f80f322d98d499eefcb060599efc5d4ecb00209c2cb54190"
-                .parse()
-                .unwrap(),
-            "3.230.33.224:9735".parse().unwrap(),
-            true,
-        ) {
-            warn!(?e, "Failed to connect to Block's public node");
-        }
-
-        if let Err(e) = node.connect(
-            "03c8e5f583585cac1de2b7503a6ccd3c12ba477cfd139cd4905be504c2f48e86bd"
-                .parse()
-                .unwrap(),
-            "34.73.189.183:9735".parse().unwrap(),
-            true,
-        ) {
-            warn!(?e, "Failed to connect to Strike's public node");
+    // On first startup, connect to the bootstrap peers
+    if !secret::exists(&args.puncture_data_dir) {
+        for peer in lsp::bootstrap_peers(&args) {
+            if let Err(e) = node.connect(peer.node_id, peer.address.clone(), true) {
+                warn!(?e, ?peer, "Failed to connect to bootstrap peer");
+            }
         }
     }
 
@@ -249,13 +386,15 @@ fn main() -> Result<()> {
 
     let event_bus = EventBus::new(1000);
 
-    let secret_key = secret::read_or_generate(&args.puncture_data_dir);
+    let passphrase = secret_passphrase(&args)?;
+
+    let secret_key = secret::read_or_generate(&args.puncture_data_dir, passphrase.as_deref())?;
 
     let builder = Endpoint::builder()
-        .secret_key(secret_key)
+        .secret_key(secret_key.clone())
         .discovery_n0()
         .discovery_dht()
-        .alpns(vec![b"puncture".to_vec()]);
+        .alpns(vec![b"puncture".to_vec(), ADMIN_ALPN.to_vec()]);
 
     let builder = match args.client_bind {
         SocketAddr::V4(addr_v4) => builder.bind_addr_v4(addr_v4),
@@ -270,10 +409,49 @@ fn main() -> Result<()> {
         node: node.clone(),
         event_bus: event_bus.clone(),
         node_id: endpoint.node_id(),
+        endpoint: endpoint.clone(),
+        secret_key,
+        cli_tokens: CliTokens::read_or_generate(&args.puncture_data_dir)?,
+        ui_password: match &args.ui_password {
+            Some(ui_password) => ui_password.clone(),
+            None => read_or_generate_token(&args.puncture_data_dir.join("ui.password"))?,
+        },
+        ui_sessions: Arc::new(DashMap::new()),
+        liability_tree: Arc::new(RwLock::new(
+            runtime.block_on(async { solvency::load(&mut *db.get_connection().await).await }),
+        )),
+        metrics: Metrics::new(),
+        settings: Arc::new(RwLock::new(settings)),
     };
 
     let ct = tokio_util::sync::CancellationToken::new();
 
+    // Keep serving the signed migration statements of rotated keys such that
+    // clients can follow us to our current node id.
+    let mut migration_tasks = Vec::new();
+
+    let migrations =
+        runtime.block_on(async { db::list_migrations(&mut *db.get_connection().await).await });
+
+    for migration in migrations {
+        let secret_key = secret::read_retired(
+            &args.puncture_data_dir,
+            &migration.old_node_id,
+            passphrase.as_deref(),
+        )?;
+
+        let endpoint = runtime.block_on(
+            Endpoint::builder()
+                .secret_key(secret_key)
+                .discovery_n0()
+                .discovery_dht()
+                .alpns(vec![b"puncture".to_vec()])
+                .bind(),
+        )?;
+
+        migration_tasks.push(runtime.spawn(client::run_migration(endpoint, migration, ct.clone())));
+    }
+
     let client_task = runtime.spawn(client::run_api(
         endpoint.clone(),
         app_state.clone(),
@@ -284,10 +462,17 @@ fn main() -> Result<()> {
 
     let ui_task = runtime.spawn(ui::run_ui(app_state.clone(), ct.clone()));
 
+    let reload_task = runtime.spawn(config::run_reload(app_state.clone(), ct.clone()));
+
+    let metrics_task = runtime.spawn(metrics::run_metrics(app_state.clone(), ct.clone()));
+
+    let liquidity_task = runtime.spawn(liquidity::run_liquidity(app_state.clone(), ct.clone()));
+
     let events_task = runtime.spawn(process_ldk_events(
         node.clone(),
         db.clone(),
         event_bus.clone(),
+        app_state.metrics.clone(),
         ct.clone(),
     ));
 
@@ -313,6 +498,24 @@ fn main() -> Result<()> {
         warn!(?e, "Failed to join UI task");
     }
 
+    if let Err(e) = runtime.block_on(metrics_task) {
+        warn!(?e, "Failed to join metrics task");
+    }
+
+    if let Err(e) = runtime.block_on(reload_task) {
+        warn!(?e, "Failed to join config reload task");
+    }
+
+    if let Err(e) = runtime.block_on(liquidity_task) {
+        warn!(?e, "Failed to join liquidity task");
+    }
+
+    for migration_task in migration_tasks {
+        if let Err(e) = runtime.block_on(migration_task) {
+            warn!(?e, "Failed to join migration task");
+        }
+    }
+
     info!("Graceful shutdown complete");
 
     Ok(())
@@ -322,6 +525,7 @@ async fn process_ldk_events(
     node: Arc<Node>,
     db: Database,
     event_bus: EventBus,
+    metrics: Metrics,
     ct: CancellationToken,
 ) {
     loop {
@@ -329,7 +533,7 @@ async fn process_ldk_events(
             event = node.next_event_async() => {
                 info!("Processing LDK Event: {:?}", event);
 
-                if let Err(e) = process_ldk_event(node.clone(), db.clone(), event_bus.clone(), event).await {
+                if let Err(e) = process_ldk_event(node.clone(), db.clone(), event_bus.clone(), &metrics, event).await {
                     warn!(?e, "Failed to process LDK event");
                 }
 
@@ -348,6 +552,7 @@ async fn process_ldk_event(
     node: Arc<Node>,
     db: Database,
     event_bus: EventBus,
+    metrics: &Metrics,
     event: Event,
 ) -> Result<()> {
     match event {
@@ -363,10 +568,12 @@ async fn process_ldk_event(
                 .context("Payment not found")?
                 .kind
             {
-                PaymentKind::Bolt11 { hash, .. } => db::get_invoice(&mut conn, hash.0)
-                    .await
-                    .context("Invoice not found")?
-                    .into_receive_record(payment_id.unwrap().0, amount_msat),
+                PaymentKind::Bolt11 { hash, .. } | PaymentKind::Bolt11Jit { hash, .. } => {
+                    db::get_invoice(&mut conn, hash.0)
+                        .await
+                        .context("Invoice not found")?
+                        .into_receive_record(payment_id.unwrap().0, amount_msat)
+                }
                 PaymentKind::Bolt12Offer { offer_id, .. } => db::get_offer(&mut conn, offer_id.0)
                     .await
                     .context("Offer not found")?
@@ -410,6 +617,8 @@ async fn process_ldk_event(
 
             info!(?record.user_pk, ?latency_ms, "payment successful");
 
+            metrics.observe_payment("successful", latency_ms);
+
             event_bus.send_balance_event(record.user_pk.clone(), balance_msat);
 
             event_bus.send_update_event(
@@ -436,12 +645,50 @@ async fn process_ldk_event(
 
             warn!(?record.user_pk, ?latency_ms, ?reason, "payment failed");
 
+            metrics.observe_payment("failed", latency_ms);
+
             event_bus.send_balance_event(record.user_pk.clone(), balance_msat);
 
             event_bus.send_update_event(record.user_pk, record.id, "failed", 0);
 
             Ok(())
         }
+        Event::PaymentForwarded {
+            prev_channel_id,
+            next_channel_id,
+            prev_node_id,
+            next_node_id,
+            total_fee_earned_msat,
+            skimmed_fee_msat,
+            claim_from_onchain_tx,
+            outbound_amount_forwarded_msat,
+            ..
+        } => {
+            info!(
+                ?next_channel_id,
+                ?total_fee_earned_msat,
+                "payment forwarded"
+            );
+
+            // The forward is recorded before the event is marked as handled, a
+            // crash in between replays the event and records it twice
+            let record = ForwardRecord {
+                id: rand::rng().random::<[u8; 16]>().as_hex().to_string(),
+                prev_channel_id: prev_channel_id.to_string(),
+                next_channel_id: next_channel_id.to_string(),
+                prev_node_id: prev_node_id.map(|node_id| node_id.to_string()),
+                next_node_id: next_node_id.map(|node_id| node_id.to_string()),
+                fee_earned_msat: total_fee_earned_msat.map(|fee| fee as i64),
+                skimmed_fee_msat: skimmed_fee_msat.map(|fee| fee as i64),
+                amount_forwarded_msat: outbound_amount_forwarded_msat.map(|amount| amount as i64),
+                claim_from_onchain_tx,
+                created_at: unix_time(),
+            };
+
+            db::create_forward(&mut *db.get_connection().await, record).await;
+
+            Ok(())
+        }
         _ => Ok(()),
     }
 }
@@ -0,0 +1,271 @@
+use std::fmt::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use axum::{
+    Router, extract::State, http::header::CONTENT_TYPE, response::IntoResponse, routing::get,
+};
+use dashmap::DashMap;
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+use crate::AppState;
+
+/// Upper bounds of the latency histogram buckets in seconds
+const LATENCY_BUCKETS: [f64; 10] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+struct Histogram {
+    buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+    sum_ms: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            buckets: Default::default(),
+            sum_ms: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    fn observe(&self, latency: Duration) {
+        for (bucket, bound) in self.buckets.iter().zip(LATENCY_BUCKETS) {
+            if latency.as_secs_f64() <= bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        self.sum_ms
+            .fetch_add(latency.as_millis() as u64, Ordering::Relaxed);
+
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        for (bucket, bound) in self.buckets.iter().zip(LATENCY_BUCKETS) {
+            writeln!(
+                out,
+                "{name}_bucket{{{labels},le=\"{bound}\"}} {}",
+                bucket.load(Ordering::Relaxed)
+            )
+            .unwrap();
+        }
+
+        let count = self.count.load(Ordering::Relaxed);
+
+        writeln!(out, "{name}_bucket{{{labels},le=\"+Inf\"}} {count}").unwrap();
+
+        writeln!(
+            out,
+            "{name}_sum{{{labels}}} {}",
+            self.sum_ms.load(Ordering::Relaxed) as f64 / 1000.0
+        )
+        .unwrap();
+
+        writeln!(out, "{name}_count{{{labels}}} {count}").unwrap();
+    }
+}
+
+/// Counters collected while the daemon is running, the remaining metrics are
+/// read from the node and the database when they are scraped.
+#[derive(Clone)]
+pub struct Metrics {
+    /// The number of open iroh connections per user
+    pub connections: Arc<DashMap<String, AtomicUsize>>,
+    /// Client RPC latencies by method and outcome
+    rpc: Arc<DashMap<(String, &'static str), Histogram>>,
+    /// Latencies of outgoing payments by final status
+    payments: Arc<DashMap<&'static str, Histogram>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            connections: Arc::new(DashMap::new()),
+            rpc: Arc::new(DashMap::new()),
+            payments: Arc::new(DashMap::new()),
+        }
+    }
+
+    pub fn observe_rpc(&self, method: &str, success: bool, latency: Duration) {
+        let status = if success { "success" } else { "error" };
+
+        self.rpc
+            .entry((method.to_string(), status))
+            .or_insert_with(Histogram::new)
+            .observe(latency);
+    }
+
+    pub fn observe_payment(&self, status: &'static str, latency_ms: i64) {
+        self.payments
+            .entry(status)
+            .or_insert_with(Histogram::new)
+            .observe(Duration::from_millis(latency_ms.max(0) as u64));
+    }
+}
+
+pub async fn run_metrics(app_state: AppState, ct: CancellationToken) {
+    let Some(metrics_bind) = app_state.args.metrics_bind else {
+        return;
+    };
+
+    let listener = TcpListener::bind(metrics_bind)
+        .await
+        .expect("Failed to bind metrics server");
+
+    info!(?metrics_bind, "Serving metrics");
+
+    let router = Router::new()
+        .route("/metrics", get(metrics))
+        .with_state(app_state);
+
+    axum::serve(listener, router)
+        .with_graceful_shutdown(ct.cancelled_owned())
+        .await
+        .expect("Failed to start metrics server");
+}
+
+async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let mut out = String::new();
+
+    let metrics = &state.metrics;
+
+    gauge(
+        &mut out,
+        "puncture_connected_users",
+        "Number of users with at least one open connection",
+        metrics
+            .connections
+            .iter()
+            .filter(|entry| entry.value().load(Ordering::Relaxed) > 0)
+            .count() as f64,
+    );
+
+    gauge(
+        &mut out,
+        "puncture_connections",
+        "Number of open client connections",
+        metrics
+            .connections
+            .iter()
+            .map(|entry| entry.value().load(Ordering::Relaxed))
+            .sum::<usize>() as f64,
+    );
+
+    header(
+        &mut out,
+        "puncture_rpc_duration_seconds",
+        "histogram",
+        "Latency of client RPC requests by method and outcome",
+    );
+
+    for entry in metrics.rpc.iter() {
+        let (method, status) = entry.key();
+
+        entry.value().render(
+            &mut out,
+            "puncture_rpc_duration_seconds",
+            &format!("method=\"{method}\",status=\"{status}\""),
+        );
+    }
+
+    header(
+        &mut out,
+        "puncture_payment_duration_seconds",
+        "histogram",
+        "Time from initiating an outgoing payment until it succeeded or failed",
+    );
+
+    for entry in metrics.payments.iter() {
+        entry.value().render(
+            &mut out,
+            "puncture_payment_duration_seconds",
+            &format!("status=\"{}\"", entry.key()),
+        );
+    }
+
+    let balances = crate::db::list_user_balances(&mut *state.db.get_connection().await).await;
+
+    gauge(
+        &mut out,
+        "puncture_users",
+        "Number of registered users",
+        balances.len() as f64,
+    );
+
+    gauge(
+        &mut out,
+        "puncture_liabilities_msat",
+        "Sum of all user balances",
+        balances.iter().map(|(_, balance)| *balance).sum::<u64>() as f64,
+    );
+
+    let channels = state.node.list_channels();
+
+    gauge(
+        &mut out,
+        "puncture_channels",
+        "Number of channels",
+        channels.len() as f64,
+    );
+
+    gauge(
+        &mut out,
+        "puncture_outbound_capacity_msat",
+        "Outbound capacity across all usable channels",
+        channels
+            .iter()
+            .filter(|c| c.is_usable)
+            .map(|c| c.outbound_capacity_msat)
+            .sum::<u64>() as f64,
+    );
+
+    gauge(
+        &mut out,
+        "puncture_inbound_capacity_msat",
+        "Inbound capacity across all usable channels",
+        channels
+            .iter()
+            .filter(|c| c.is_usable)
+            .map(|c| c.inbound_capacity_msat)
+            .sum::<u64>() as f64,
+    );
+
+    gauge(
+        &mut out,
+        "puncture_onchain_balance_sats",
+        "Total balance of the on-chain wallet",
+        state.node.list_balances().total_onchain_balance_sats as f64,
+    );
+
+    header(
+        &mut out,
+        "puncture_event_bus_lagged_total",
+        "counter",
+        "Number of times a client event stream fell behind and missed events",
+    );
+
+    writeln!(
+        out,
+        "puncture_event_bus_lagged_total {}",
+        state.event_bus.lagged()
+    )
+    .unwrap();
+
+    ([(CONTENT_TYPE, "text/plain; version=0.0.4")], out)
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# HELP {name} {help}").unwrap();
+
+    writeln!(out, "# TYPE {name} {kind}").unwrap();
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    header(out, name, "gauge", help);
+
+    writeln!(out, "{name} {value}").unwrap();
+}
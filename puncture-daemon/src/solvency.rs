@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use bitcoin::hex::DisplayHex;
+use diesel::SqliteConnection;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use tracing::{info, warn};
+
+use puncture_cli_core::{PublishedLiabilities, SolvencyResponse};
+use puncture_client_core::{LiabilityNode, LiabilityProof, LiabilityProofStep};
+use puncture_core::unix_time;
+use puncture_daemon_db::models::{LiabilityLeafRecord, LiabilityPublicationRecord};
+
+use crate::AppState;
+
+/// Merkle sum tree over the balances of all users at the time it was
+/// published. The root commits to our total liabilities, while every user can
+/// request a proof that their balance is included in it.
+pub struct LiabilityTree {
+    /// The levels of the tree from the leaves to the root
+    levels: Vec<Vec<LiabilityNode>>,
+    /// The leaf index, nonce and balance of every user
+    leaves: HashMap<String, (usize, String, u64)>,
+    created_at: i64,
+}
+
+impl LiabilityTree {
+    pub fn build(mut balances: Vec<(String, u64)>) -> Self {
+        // The leaf position must not reveal the registration order of users
+        balances.shuffle(&mut rand::rng());
+
+        let leaves = balances
+            .into_iter()
+            .map(|(user_pk, balance_msat)| {
+                let nonce = rand::rng().random::<[u8; 16]>().as_hex().to_string();
+
+                (user_pk, nonce, balance_msat)
+            })
+            .collect();
+
+        Self::from_leaves(leaves, unix_time())
+    }
+
+    /// Build the tree from the user public key, nonce and balance of every
+    /// leaf in order
+    fn from_leaves(leaves: Vec<(String, String, u64)>, created_at: i64) -> Self {
+        let mut leaf_map = HashMap::new();
+
+        let mut level = Vec::with_capacity(leaves.len());
+
+        for (index, (user_pk, nonce, balance_msat)) in leaves.into_iter().enumerate() {
+            level.push(LiabilityNode::leaf(&user_pk, &nonce, balance_msat));
+
+            leaf_map.insert(user_pk, (index, nonce, balance_msat));
+        }
+
+        if level.is_empty() {
+            level.push(LiabilityNode::empty());
+        }
+
+        let mut levels = vec![level];
+
+        while levels.last().unwrap().len() > 1 {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| {
+                    LiabilityNode::parent(&pair[0], pair.get(1).unwrap_or(&LiabilityNode::empty()))
+                        .expect("Total liabilities exceed u64::MAX")
+                })
+                .collect();
+
+            levels.push(next);
+        }
+
+        Self {
+            levels,
+            leaves: leaf_map,
+            created_at,
+        }
+    }
+
+    pub fn root(&self) -> LiabilityNode {
+        self.levels.last().unwrap()[0]
+    }
+
+    pub fn published(&self) -> PublishedLiabilities {
+        PublishedLiabilities {
+            root_hash: self.root().hash.to_string(),
+            liabilities_msat: self.root().sum_msat,
+            created_at: self.created_at,
+        }
+    }
+
+    pub fn proof(&self, user_pk: &str) -> Option<LiabilityProof> {
+        let (mut index, nonce, balance_msat) = self.leaves.get(user_pk)?.clone();
+
+        let mut path = Vec::new();
+
+        for level in &self.levels[..self.levels.len() - 1] {
+            path.push(LiabilityProofStep {
+                sibling: level
+                    .get(index ^ 1)
+                    .copied()
+                    .unwrap_or(LiabilityNode::empty()),
+                sibling_is_left: index % 2 == 1,
+            });
+
+            index /= 2;
+        }
+
+        Some(LiabilityProof {
+            root: self.root(),
+            balance_msat,
+            nonce,
+            path,
+            created_at: self.created_at,
+        })
+    }
+}
+
+/// Compare the funds we control with the balances we owe to our users
+pub async fn report(state: &AppState) -> SolvencyResponse {
+    let onchain_msat = state.node.list_balances().total_onchain_balance_sats * 1000;
+
+    let lightning_msat = state
+        .node
+        .list_channels()
+        .into_iter()
+        .map(|c| c.outbound_capacity_msat)
+        .sum::<u64>();
+
+    let balances = crate::db::list_user_balances(&mut *state.db.get_connection().await).await;
+
+    let liabilities_msat = balances.iter().map(|(_, balance)| balance).sum::<u64>();
+
+    let assets_msat = onchain_msat + lightning_msat;
+
+    SolvencyResponse {
+        onchain_msat,
+        lightning_msat,
+        assets_msat,
+        liabilities_msat,
+        surplus_msat: assets_msat as i64 - liabilities_msat as i64,
+        user_count: balances.len() as u64,
+        published: state
+            .liability_tree
+            .read()
+            .await
+            .as_ref()
+            .map(LiabilityTree::published),
+    }
+}
+
+/// Load the liability tree published before the last restart
+pub async fn load(conn: &mut SqliteConnection) -> Option<LiabilityTree> {
+    let (publication, leaves) = crate::db::get_liability_tree(conn).await?;
+
+    let tree = LiabilityTree::from_leaves(
+        leaves
+            .into_iter()
+            .map(|leaf| (leaf.user_pk, leaf.nonce, leaf.balance_msat as u64))
+            .collect(),
+        publication.created_at,
+    );
+
+    if tree.root().hash.to_string() != publication.root_hash {
+        warn!(
+            root_hash = publication.root_hash,
+            "Stored liability tree does not match its published root, dropping it"
+        );
+
+        return None;
+    }
+
+    Some(tree)
+}
+
+/// Build and publish a new liability tree, replacing the previous one
+pub async fn publish(state: &AppState) -> Result<SolvencyResponse, String> {
+    let mut conn = state.db.get_connection().await;
+
+    let balances = crate::db::list_user_balances(&mut conn).await;
+
+    let tree = LiabilityTree::build(balances);
+
+    info!(root = ?tree.root(), "Publishing liability tree");
+
+    crate::db::save_liability_tree(
+        &mut conn,
+        LiabilityPublicationRecord {
+            created_at: tree.created_at,
+            root_hash: tree.root().hash.to_string(),
+            liabilities_msat: tree.root().sum_msat as i64,
+        },
+        tree.leaves
+            .iter()
+            .map(
+                |(user_pk, (index, nonce, balance_msat))| LiabilityLeafRecord {
+                    user_pk: user_pk.clone(),
+                    leaf_index: *index as i64,
+                    nonce: nonce.clone(),
+                    balance_msat: *balance_msat as i64,
+                },
+            )
+            .collect(),
+    )
+    .await?;
+
+    drop(conn);
+
+    *state.liability_tree.write().await = Some(tree);
+
+    Ok(report(state).await)
+}
@@ -0,0 +1,120 @@
+use axum::{
+    Extension,
+    extract::{Query, State},
+    response::Html,
+};
+use chrono::DateTime;
+use maud::{Markup, html};
+use serde::Deserialize;
+
+use puncture_cli_core::{AuditEntry, AuditListRequest};
+
+use super::auth::UiSession;
+use super::shared::base_template;
+use crate::AppState;
+
+#[derive(Deserialize)]
+pub struct AuditFilter {
+    pub actor: Option<String>,
+    pub action: Option<String>,
+}
+
+pub async fn audit_page(
+    State(state): State<AppState>,
+    Extension(session): Extension<UiSession>,
+    Query(filter): Query<AuditFilter>,
+) -> Html<String> {
+    // Empty form fields are submitted as empty strings
+    let actor = filter.actor.filter(|actor| !actor.is_empty());
+
+    let action = filter.action.filter(|action| !action.is_empty());
+
+    let entries = crate::db::list_audit_entries(
+        &mut *state.db.get_connection().await,
+        AuditListRequest {
+            actor: actor.clone(),
+            action: action.clone(),
+            since_days: None,
+            failed: false,
+            limit: 100,
+        },
+    )
+    .await;
+
+    let html = audit_template(&entries, actor, action, &session.csrf_token);
+
+    Html(html.into_string())
+}
+
+fn audit_template(
+    entries: &[AuditEntry],
+    actor: Option<String>,
+    action: Option<String>,
+    csrf_token: &str,
+) -> Markup {
+    let content = html! {
+        div class="card" {
+            div class="card-body" {
+                h5 class="card-title" { "Audit Log" }
+                @if entries.is_empty() {
+                    div class="p-4 text-center text-muted" {
+                        "No matching audit entries."
+                    }
+                } @else {
+                    div class="table-responsive" {
+                        table class="table table-sm align-middle" {
+                            thead {
+                                tr {
+                                    th { "Time" }
+                                    th { "Actor" }
+                                    th { "Action" }
+                                    th { "Status" }
+                                    th { "Request" }
+                                }
+                            }
+                            tbody {
+                                @for entry in entries {
+                                    tr {
+                                        td class="text-muted font-monospace small text-nowrap" {
+                                            (DateTime::from_timestamp_millis(entry.created_at)
+                                                .unwrap()
+                                                .format("%Y-%m-%d %H:%M:%S"))
+                                        }
+                                        td class="font-monospace small" { (entry.actor) }
+                                        td class="font-monospace small" { (entry.action) }
+                                        td {
+                                            @if entry.status < 400 {
+                                                span class="badge bg-success" { (entry.status) }
+                                            } @else {
+                                                span class="badge bg-danger" { (entry.status) }
+                                            }
+                                        }
+                                        td class="font-monospace small text-break" { (entry.request) }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    };
+
+    let action_sidebar = html! {
+        form method="get" action="/audit" {
+            div class="mb-3" {
+                label for="audit-actor" class="form-label" { "Actor" }
+                input type="text" class="form-control font-monospace" id="audit-actor" name="actor" value=[actor] placeholder="cli, ui or node" {}
+                div class="form-text" { "Only show entries by actors starting with this prefix" }
+            }
+            div class="mb-3" {
+                label for="audit-action" class="form-label" { "Action" }
+                input type="text" class="form-control font-monospace" id="audit-action" name="action" value=[action] placeholder="/onchain/drain" {}
+            }
+
+            button type="submit" class="btn btn-outline-primary w-100" { "Filter" }
+        }
+    };
+
+    base_template("Audit", "/audit", csrf_token, content, action_sidebar)
+}
@@ -0,0 +1,242 @@
+use axum::Form;
+use axum::body::{Body, to_bytes};
+use axum::extract::{Request, State};
+use axum::http::header::{COOKIE, LOCATION, SET_COOKIE};
+use axum::http::{HeaderMap, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use bitcoin::hex::DisplayHex;
+use maud::{DOCTYPE, Markup, html};
+use rand::Rng;
+use serde::Deserialize;
+use tracing::warn;
+
+use puncture_core::unix_time;
+
+use crate::AppState;
+use crate::cli::auth::constant_time_eq;
+
+const SESSION_COOKIE: &str = "puncture_session";
+
+const CSRF_HEADER: &str = "X-CSRF-Token";
+
+const SESSION_DURATION_MS: i64 = 24 * 60 * 60 * 1000;
+
+#[derive(Clone)]
+pub struct UiSession {
+    /// Sent by htmx with every request, see base_template
+    pub csrf_token: String,
+    pub expires_at: i64,
+}
+
+#[derive(Deserialize)]
+pub struct LoginForm {
+    pub password: String,
+}
+
+fn session_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .find_map(|cookie| cookie.trim().strip_prefix(&format!("{SESSION_COOKIE}=")))
+        .map(|id| id.to_string())
+}
+
+/// Require a valid session for every page and a matching CSRF token for every
+/// form submission, which are recorded in the audit log.
+pub async fn require_session(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let session = session_id(request.headers()).and_then(|id| {
+        state
+            .ui_sessions
+            .get(&id)
+            .map(|session| (id, session.clone()))
+            .filter(|(_, session)| session.expires_at > unix_time())
+    });
+
+    let Some((session_id, session)) = session else {
+        if request.method() == Method::GET {
+            return Redirect::to("/login").into_response();
+        }
+
+        return (StatusCode::UNAUTHORIZED, [("HX-Redirect", "/login")]).into_response();
+    };
+
+    if request.method() == Method::GET {
+        request.extensions_mut().insert(session);
+
+        return next.run(request).await;
+    }
+
+    let csrf_valid = request
+        .headers()
+        .get(CSRF_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|token| constant_time_eq(token, &session.csrf_token));
+
+    if !csrf_valid {
+        warn!(
+            path = request.uri().path(),
+            "rejected ui form with invalid csrf token"
+        );
+
+        return (StatusCode::FORBIDDEN, "Invalid CSRF token").into_response();
+    }
+
+    let action = request.uri().path().to_string();
+
+    request.extensions_mut().insert(session);
+
+    let (parts, body) = request.into_parts();
+
+    let Ok(form) = to_bytes(body, 1_000_000).await else {
+        return (StatusCode::PAYLOAD_TOO_LARGE, "Form too large").into_response();
+    };
+
+    let response = next
+        .run(Request::from_parts(parts, Body::from(form.clone())))
+        .await;
+
+    crate::db::create_audit_entry(
+        &mut *state.db.get_connection().await,
+        format!("ui:{}", &session_id[..8]),
+        action,
+        String::from_utf8_lossy(&form).to_string(),
+        response.status().as_u16(),
+        None,
+    )
+    .await;
+
+    response
+}
+
+pub async fn login_page() -> Html<String> {
+    Html(login_template(None).into_string())
+}
+
+pub async fn login_submit(State(state): State<AppState>, Form(form): Form<LoginForm>) -> Response {
+    if !constant_time_eq(&form.password, &state.ui_password) {
+        warn!("rejected ui login with invalid password");
+
+        crate::db::create_audit_entry(
+            &mut *state.db.get_connection().await,
+            "ui".to_string(),
+            "/login".to_string(),
+            String::new(),
+            StatusCode::UNAUTHORIZED.as_u16(),
+            None,
+        )
+        .await;
+
+        // Slow down brute forcing the password
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+
+        return (
+            StatusCode::UNAUTHORIZED,
+            Html(login_template(Some("Invalid password")).into_string()),
+        )
+            .into_response();
+    }
+
+    state
+        .ui_sessions
+        .retain(|_, session| session.expires_at > unix_time());
+
+    let session_id = rand::rng().random::<[u8; 32]>().as_hex().to_string();
+
+    state.ui_sessions.insert(
+        session_id.clone(),
+        UiSession {
+            csrf_token: rand::rng().random::<[u8; 32]>().as_hex().to_string(),
+            expires_at: unix_time() + SESSION_DURATION_MS,
+        },
+    );
+
+    crate::db::create_audit_entry(
+        &mut *state.db.get_connection().await,
+        format!("ui:{}", &session_id[..8]),
+        "/login".to_string(),
+        String::new(),
+        StatusCode::SEE_OTHER.as_u16(),
+        None,
+    )
+    .await;
+
+    (
+        StatusCode::SEE_OTHER,
+        [
+            (
+                SET_COOKIE,
+                format!(
+                    "{SESSION_COOKIE}={session_id}; Path=/; HttpOnly; SameSite=Strict; Max-Age={}",
+                    SESSION_DURATION_MS / 1000
+                ),
+            ),
+            (LOCATION, "/".to_string()),
+        ],
+    )
+        .into_response()
+}
+
+pub async fn logout_submit(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Some(session_id) = session_id(&headers) {
+        state.ui_sessions.remove(&session_id);
+    }
+
+    (
+        StatusCode::OK,
+        [
+            (
+                SET_COOKIE.as_str(),
+                format!("{SESSION_COOKIE}=; Path=/; HttpOnly; SameSite=Strict; Max-Age=0"),
+            ),
+            ("HX-Redirect", "/login".to_string()),
+        ],
+    )
+        .into_response()
+}
+
+fn login_template(error: Option<&str>) -> Markup {
+    html! {
+        (DOCTYPE)
+        html {
+            head {
+                meta charset="utf-8";
+                meta name="viewport" content="width=device-width, initial-scale=1";
+                title { "Login - Puncture Dashboard" }
+                link href="https://cdn.jsdelivr.net/npm/bootstrap@5.3.0/dist/css/bootstrap.min.css" rel="stylesheet";
+            }
+            body class="bg-light" {
+                div class="container" {
+                    div class="row justify-content-center" {
+                        div class="col-md-4 pt-5" {
+                            div class="card" {
+                                div class="card-body" {
+                                    h5 class="card-title mb-3" { "Puncture Dashboard" }
+
+                                    @if let Some(err) = error {
+                                        div class="alert alert-danger" { (err) }
+                                    }
+
+                                    form method="post" action="/login" {
+                                        div class="mb-3" {
+                                            label for="login-password" class="form-label" { "Password" }
+                                            input type="password" class="form-control" id="login-password" name="password" required autofocus {}
+                                        }
+
+                                        button type="submit" class="btn btn-outline-primary w-100" { "Login" }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
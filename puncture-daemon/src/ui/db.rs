@@ -18,6 +18,7 @@ pub async fn create_invite(
         user_limit: user_limit as i64,
         expires_at: unix_time() + expiry_secs as i64 * 1000,
         created_at: unix_time(),
+        revoked_at: None,
     };
 
     diesel::insert_into(invite::table)
@@ -41,6 +42,11 @@ pub async fn list_users(conn: &mut SqliteConnection) -> Vec<UserInfo> {
             balance_msat: crate::db::user_balance(conn, user_record.user_pk.clone()).await,
             recovery_name: user_record.recovery_name,
             created_at: user_record.created_at,
+            status: user_record
+                .status
+                .parse()
+                .expect("Invalid user status in database"),
+            status_reason: user_record.status_reason,
         });
     }
 
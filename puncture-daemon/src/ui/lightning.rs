@@ -1,5 +1,5 @@
 use axum::{
-    Form,
+    Extension, Form,
     extract::State,
     response::{Html, IntoResponse},
 };
@@ -8,20 +8,35 @@ use ldk_node::UserChannelId;
 use maud::{Markup, html};
 use serde::Deserialize;
 
+use puncture_cli_core::{
+    ChannelConfigInfo, ChannelOrderInfo, CreateChannelOrderRequest, ForwardingReportResponse,
+    OrderPaymentMethod, SolvencyResponse, UpdateChannelConfigRequest,
+};
+
+use super::auth::UiSession;
 use super::shared::{
-    base_template, copyable_hex_input, format_sats, parse_node_id, parse_socket_address,
-    qr_code_with_copy, success_message, success_replacement,
+    base_template, copyable_hex_input, format_sats, format_timestamp, parse_node_id,
+    parse_socket_address, qr_code_with_copy, success_message, success_replacement,
 };
 use crate::AppState;
+use crate::lsp::Lsps1Provider;
 
+#[allow(clippy::too_many_arguments)]
 pub fn lightning_template(
     node_id: &str,
     total_inbound_capacity_msat: u64,
     total_outbound_capacity_msat: u64,
     channels: &[ldk_node::ChannelDetails],
     peers: &[ldk_node::PeerDetails],
+    solvency: &SolvencyResponse,
+    providers: &[Lsps1Provider],
+    orders: &[ChannelOrderInfo],
+    forwarding: &ForwardingReportResponse,
+    csrf_token: &str,
 ) -> Markup {
     let content = html! {
+        (solvency_card(solvency))
+
         // Overview Cards
         div class="row g-4 mb-4" {
             div class="col-6" {
@@ -106,9 +121,42 @@ pub fn lightning_template(
                                                     td class="fw-bold" { "Usable" }
                                                     td { (channel.is_usable) }
                                                 }
+                                                tr {
+                                                    td class="fw-bold" { "Forwarding Fee" }
+                                                    td { (channel.config.forwarding_fee_base_msat) " msat + " (channel.config.forwarding_fee_proportional_millionths) " ppm" }
+                                                }
+                                                tr {
+                                                    td class="fw-bold" { "CLTV Expiry Delta" }
+                                                    td { (channel.config.cltv_expiry_delta) }
+                                                }
                                             }
                                         }
-                                        div class="d-flex justify-content-end" {
+                                        div class="d-flex justify-content-end gap-3" {
+                                            div class="accordion" id={(format!("feeAccordion-{}", i))} style="width: 300px;" {
+                                                div class="accordion-item" {
+                                                    h2 class="accordion-header" {
+                                                        button class="accordion-button collapsed" type="button"
+                                                               data-bs-toggle="collapse"
+                                                               data-bs-target={(format!("#feeCollapse-{}", i))}
+                                                               aria-expanded="false"
+                                                               aria-controls={(format!("feeCollapse-{}", i))} {
+                                                            "Fee Policy"
+                                                        }
+                                                    }
+                                                    div id={(format!("feeCollapse-{}", i))} class="accordion-collapse collapse"
+                                                         data-bs-parent={(format!("#feeAccordion-{}", i))} {
+                                                        div class="accordion-body" {
+                                                            (channel_config_form(
+                                                                &channel.user_channel_id.0.to_be_bytes().as_hex().to_string(),
+                                                                &channel.counterparty_node_id.to_string(),
+                                                                &crate::forwarding::channel_config_info(&channel.config),
+                                                                i,
+                                                                None
+                                                            ))
+                                                        }
+                                                    }
+                                                }
+                                            }
                                             div class="accordion" id={(format!("closeAccordion-{}", i))} style="width: 300px;" {
                                                 div class="accordion-item" {
                                                     h2 class="accordion-header" {
@@ -143,6 +191,14 @@ pub fn lightning_template(
             }
         }
 
+        @if !orders.is_empty() {
+            (channel_orders_card(orders))
+        }
+
+        @if forwarding.forwards > 0 {
+            (forwarding_card(forwarding))
+        }
+
         // Connected Peers
         div class="card h-100 overflow-hidden" {
             div class="card-body" {
@@ -253,7 +309,20 @@ pub fn lightning_template(
                 }
                 div id="requestChannelCollapse" class="accordion-collapse collapse" data-bs-parent="#lightningActionsAccordion" {
                     div class="accordion-body" {
-                        (request_channel_form(None))
+                        (request_channel_form(providers, None))
+                    }
+                }
+            }
+
+            div class="accordion-item" {
+                h2 class="accordion-header" {
+                    button class="accordion-button collapsed" type="button" data-bs-toggle="collapse" data-bs-target="#orderChannelCollapse" aria-expanded="false" aria-controls="orderChannelCollapse" {
+                        "Buy Channel (LSPS1)"
+                    }
+                }
+                div id="orderChannelCollapse" class="accordion-collapse collapse" data-bs-parent="#lightningActionsAccordion" {
+                    div class="accordion-body" {
+                        (order_channel_form(None))
                     }
                 }
             }
@@ -273,7 +342,13 @@ pub fn lightning_template(
         }
     };
 
-    base_template("Lightning", "/lightning", content, action_sidebar)
+    base_template(
+        "Lightning",
+        "/lightning",
+        csrf_token,
+        content,
+        action_sidebar,
+    )
 }
 
 // Form structs (reusing existing ones)
@@ -288,11 +363,40 @@ pub struct OpenChannelForm {
 
 #[derive(Deserialize)]
 pub struct RequestChannelForm {
+    pub provider: String,
+    pub lsp_balance_sat: u64,
+    #[serde(default)]
+    pub public: bool,
+}
+
+#[derive(Deserialize)]
+pub struct OrderChannelForm {
     pub lsp_balance_sat: u64,
     #[serde(default)]
     pub public: bool,
 }
 
+#[derive(Deserialize)]
+pub struct ChannelOrderForm {
+    pub order_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct PayChannelOrderForm {
+    pub order_id: String,
+    pub method: OrderPaymentMethod,
+}
+
+#[derive(Deserialize)]
+pub struct ChannelConfigForm {
+    pub user_channel_id: String,
+    pub counterparty_node_id: String,
+    pub forwarding_fee_base_msat: u32,
+    pub forwarding_fee_proportional_millionths: u32,
+    pub cltv_expiry_delta: u16,
+    pub max_dust_htlc_exposure_msat: String,
+}
+
 #[derive(Deserialize)]
 pub struct CloseChannelForm {
     pub user_channel_id: String,
@@ -315,7 +419,10 @@ pub struct DisconnectPeerForm {
 }
 
 // Page handler
-pub async fn lightning_page(State(state): State<AppState>) -> impl IntoResponse {
+pub async fn lightning_page(
+    State(state): State<AppState>,
+    Extension(session): Extension<UiSession>,
+) -> impl IntoResponse {
     let channels = state.node.list_channels();
     let peers = state.node.list_peers();
 
@@ -338,11 +445,63 @@ pub async fn lightning_page(State(state): State<AppState>) -> impl IntoResponse
             total_outbound_capacity_msat,
             &channels,
             &peers,
+            &crate::solvency::report(&state).await,
+            &crate::lsp::lsps1_providers(&state.args),
+            &crate::db::list_channel_orders(&mut *state.db.get_connection().await).await,
+            &crate::forwarding::report(&state, None).await,
+            &session.csrf_token,
         )
         .into_string(),
     )
 }
 
+fn solvency_card(solvency: &SolvencyResponse) -> Markup {
+    html! {
+        div class="card overflow-hidden mb-4" {
+            div class="card-body" {
+                h5 class="card-title" { "Solvency" }
+
+                @if solvency.surplus_msat < 0 {
+                    div class="alert alert-danger" {
+                        "User balances exceed our assets by "
+                        (format_sats(solvency.surplus_msat.unsigned_abs() / 1000)) " ₿"
+                    }
+                } @else if solvency.lightning_msat < solvency.liabilities_msat {
+                    div class="alert alert-warning" {
+                        "Outbound capacity does not cover all user balances, users may not be able to withdraw via lightning at the same time"
+                    }
+                }
+
+                div class="row text-center" {
+                    div class="col-4" {
+                        div class="text-muted" { "Assets" }
+                        div class="fs-4" { (format_sats(solvency.assets_msat / 1000)) " ₿" }
+                    }
+                    div class="col-4" {
+                        div class="text-muted" { "Liabilities" }
+                        div class="fs-4" { (format_sats(solvency.liabilities_msat / 1000)) " ₿" }
+                    }
+                    div class="col-4" {
+                        div class="text-muted" { "Surplus" }
+                        div class="fs-4" {
+                            @if solvency.surplus_msat < 0 { "-" }
+                            (format_sats(solvency.surplus_msat.unsigned_abs() / 1000)) " ₿"
+                        }
+                    }
+                }
+
+                @if let Some(published) = &solvency.published {
+                    p class="text-muted small mt-3 mb-0" {
+                        "Liabilities of " (format_sats(published.liabilities_msat / 1000)) " ₿ published on "
+                        (format_timestamp(published.created_at)) " with root "
+                        span class="font-monospace" { (published.root_hash) }
+                    }
+                }
+            }
+        }
+    }
+}
+
 fn open_channel_form(error: Option<&str>) -> Markup {
     html! {
         form hx-post="/lightning/channel/open"
@@ -376,7 +535,7 @@ fn open_channel_form(error: Option<&str>) -> Markup {
     }
 }
 
-fn request_channel_form(error: Option<&str>) -> Markup {
+fn request_channel_form(providers: &[Lsps1Provider], error: Option<&str>) -> Markup {
     html! {
         form hx-post="/lightning/channel/request"
              hx-target="this"
@@ -386,6 +545,14 @@ fn request_channel_form(error: Option<&str>) -> Markup {
                 div class="alert alert-danger" { (err) }
             }
 
+            div class="mb-3" {
+                label for="request-provider" class="form-label" { "Provider" }
+                select class="form-select" id="request-provider" name="provider" required {
+                    @for provider in providers {
+                        option value=(provider.name) { (provider.name) }
+                    }
+                }
+            }
             div class="mb-3" {
                 label for="request-amount" class="form-label" { "Amount (sats)" }
                 input type="number" class="form-control" id="request-amount" name="lsp_balance_sat" required placeholder="1000000" {}
@@ -401,6 +568,141 @@ fn request_channel_form(error: Option<&str>) -> Markup {
     }
 }
 
+fn channel_orders_card(orders: &[ChannelOrderInfo]) -> Markup {
+    html! {
+        div class="card overflow-hidden mb-4" {
+            div class="card-body" {
+                h5 class="card-title" { "Channel Orders" }
+                table class="table table-sm align-middle mb-0" {
+                    thead {
+                        tr {
+                            th { "Created" }
+                            th { "Inbound" }
+                            th { "Fee" }
+                            th { "State" }
+                            th {}
+                        }
+                    }
+                    tbody {
+                        @for order in orders {
+                            tr {
+                                td { (format_timestamp(order.created_at)) }
+                                td { (format_sats(order.lsp_balance_sat)) " ₿" }
+                                td { (format_sats(order.fee_total_sat)) " ₿" }
+                                td {
+                                    @if order.funding_outpoint.is_some() {
+                                        span class="badge bg-success" { "Channel Opened" }
+                                    } @else {
+                                        @match order.payment_state.as_str() {
+                                            "paid" => span class="badge bg-info" { "Paid" },
+                                            "refunded" => span class="badge bg-secondary" { "Refunded" },
+                                            _ if order.payment.is_some() => span class="badge bg-warning text-dark" { "Payment Sent" },
+                                            _ => span class="badge bg-light text-dark" { "Awaiting Payment" },
+                                        }
+                                    }
+                                }
+                                td style="width: 1px; white-space: nowrap;" {
+                                    @if order.payment.is_none() && order.payment_state == "expect_payment" {
+                                        (pay_channel_order_form(order, None))
+                                    } @else if order.funding_outpoint.is_none() {
+                                        (refresh_channel_order_form(&order.order_id, None))
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn order_channel_form(error: Option<&str>) -> Markup {
+    html! {
+        form hx-post="/lightning/order/create"
+             hx-target="this"
+             hx-swap="outerHTML" {
+
+            @if let Some(err) = error {
+                div class="alert alert-danger" { (err) }
+            }
+
+            div class="mb-3" {
+                label for="order-amount" class="form-label" { "Amount (sats)" }
+                input type="number" class="form-control" id="order-amount" name="lsp_balance_sat" required placeholder="1000000" {}
+            }
+            div class="mb-3" {
+                div class="form-check" {
+                    input class="form-check-input" type="checkbox" id="order-public" name="public" value="true" {}
+                    label class="form-check-label" for="order-public" { "Public Channel" }
+                }
+            }
+            button type="submit" class="btn btn-outline-primary w-100" { "Get Quote" }
+        }
+    }
+}
+
+fn pay_channel_order_form(order: &ChannelOrderInfo, error: Option<&str>) -> Markup {
+    html! {
+        form hx-post="/lightning/order/pay"
+             hx-target="this"
+             hx-swap="outerHTML" {
+
+            @if let Some(err) = error {
+                div class="alert alert-danger" { (err) }
+            }
+
+            input type="hidden" name="order_id" value=(order.order_id) {}
+            div class="btn-group w-100" {
+                @if order.invoice.is_some() {
+                    button type="submit" name="method" value="lightning" class="btn btn-outline-primary" { "Pay via Lightning" }
+                }
+                @if order.onchain_address.is_some() {
+                    button type="submit" name="method" value="onchain" class="btn btn-outline-primary" { "Pay On-chain" }
+                }
+            }
+        }
+    }
+}
+
+fn refresh_channel_order_form(order_id: &str, error: Option<&str>) -> Markup {
+    html! {
+        form hx-post="/lightning/order/refresh"
+             hx-target="this"
+             hx-swap="outerHTML" {
+
+            @if let Some(err) = error {
+                div class="alert alert-danger" { (err) }
+            }
+
+            input type="hidden" name="order_id" value=(order_id) {}
+            button type="submit" class="btn btn-outline-secondary btn-sm" { "Refresh" }
+        }
+    }
+}
+
+fn channel_order_quote(order: &ChannelOrderInfo) -> Markup {
+    html! {
+        table class="table table-sm table-borderless mb-3" {
+            tbody {
+                tr {
+                    td class="fw-bold" { "Inbound" }
+                    td { (format_sats(order.lsp_balance_sat)) " ₿" }
+                }
+                tr {
+                    td class="fw-bold" { "Fee" }
+                    td { (format_sats(order.fee_total_sat)) " ₿" }
+                }
+                tr {
+                    td class="fw-bold" { "Total" }
+                    td { (format_sats(order.order_total_sat)) " ₿" }
+                }
+            }
+        }
+        (pay_channel_order_form(order, None))
+    }
+}
+
 fn connect_peer_form(error: Option<&str>) -> Markup {
     html! {
         form hx-post="/lightning/peer/connect"
@@ -448,6 +750,83 @@ fn disconnect_peer_form(counterparty_node_id: &str, error: Option<&str>) -> Mark
     }
 }
 
+fn forwarding_card(forwarding: &ForwardingReportResponse) -> Markup {
+    html! {
+        div class="card overflow-hidden mb-4" {
+            div class="card-body" {
+                h5 class="card-title" { "Forwarding Revenue" }
+                p class="card-text" {
+                    (format_sats(forwarding.fee_earned_msat / 1000)) " ₿ earned by forwarding "
+                    (forwarding.forwards) " payments of " (format_sats(forwarding.amount_forwarded_msat / 1000)) " ₿ in total."
+                }
+                table class="table table-sm align-middle mb-0" {
+                    thead {
+                        tr {
+                            th { "Outgoing Channel" }
+                            th { "Forwards" }
+                            th { "Forwarded" }
+                            th { "Earned" }
+                        }
+                    }
+                    tbody {
+                        @for summary in &forwarding.channels {
+                            tr {
+                                td class="font-monospace small text-break" { (summary.channel_id) }
+                                td { (summary.forwards) }
+                                td class="text-nowrap" { (format_sats(summary.amount_forwarded_msat / 1000)) " ₿" }
+                                td class="text-nowrap" { (format_sats(summary.fee_earned_msat / 1000)) " ₿" }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn channel_config_form(
+    user_channel_id: &str,
+    counterparty_node_id: &str,
+    config: &ChannelConfigInfo,
+    index: usize,
+    error: Option<&str>,
+) -> Markup {
+    html! {
+        form hx-post="/lightning/channel/config"
+             hx-target="this"
+             hx-swap="outerHTML" {
+
+            @if let Some(err) = error {
+                div class="alert alert-danger" { (err) }
+            }
+
+            input type="hidden" name="user_channel_id" value=(user_channel_id) {}
+            input type="hidden" name="counterparty_node_id" value=(counterparty_node_id) {}
+
+            div class="mb-2" {
+                label for={(format!("feeBase-{}", index))} class="form-label small" { "Base Fee (msat)" }
+                input type="number" class="form-control form-control-sm" id={(format!("feeBase-{}", index))} name="forwarding_fee_base_msat" required value=(config.forwarding_fee_base_msat) {}
+            }
+            div class="mb-2" {
+                label for={(format!("feeRate-{}", index))} class="form-label small" { "Fee Rate (ppm)" }
+                input type="number" class="form-control form-control-sm" id={(format!("feeRate-{}", index))} name="forwarding_fee_proportional_millionths" required value=(config.forwarding_fee_proportional_millionths) {}
+            }
+            div class="mb-2" {
+                label for={(format!("cltvDelta-{}", index))} class="form-label small" { "CLTV Expiry Delta" }
+                input type="number" class="form-control form-control-sm" id={(format!("cltvDelta-{}", index))} name="cltv_expiry_delta" required value=(config.cltv_expiry_delta) {}
+            }
+            div class="mb-3" {
+                label for={(format!("maxDust-{}", index))} class="form-label small" { "Max Dust Exposure (msat)" }
+                input type="number" class="form-control form-control-sm" id={(format!("maxDust-{}", index))} name="max_dust_htlc_exposure_msat" value=[config.max_dust_htlc_exposure_msat] placeholder="Fee rate based" {}
+            }
+
+            button type="submit" class="btn btn-outline-primary w-100" {
+                "Update"
+            }
+        }
+    }
+}
+
 fn close_channel_form(
     user_channel_id: &str,
     counterparty_node_id: &str,
@@ -515,65 +894,19 @@ async fn try_request_channel(
     state: &AppState,
     form: &RequestChannelForm,
 ) -> Result<String, String> {
-    // Connect to Megalith LSP
-    state
-        .node
-        .connect(
-            "038a9e56512ec98da2b5789761f7af8f280baf98a09282360cd6ff1381b5e889bf"
-                .parse()
-                .unwrap(),
-            "64.23.162.51:9735".parse().unwrap(),
-            true,
-        )
-        .map_err(|_| "Failed to connect to Megalith LSP node".to_string())
-        .ok();
-
-    let client = reqwest::Client::new();
-
-    // Create request payload for Megalith LSPS1 API
-    let payload = serde_json::json!({
-        "lsp_balance_sat": form.lsp_balance_sat.to_string(),
-        "client_balance_sat": "0",
-        "required_channel_confirmations": 0,
-        "funding_confirms_within_blocks": 6,
-        "channel_expiry_blocks": 13140,
-        "token": "",
-        "refund_on_chain_address": null,
-        "announce_channel": form.public,
-        "public_key": state.node.node_id().to_string()
-    });
-
-    // Make HTTP request to Megalith LSPS1 API
-    let response = client
-        .post("https://megalithic.me/api/lsps1/v1/create_order")
-        .header("Content-Type", "application/json")
-        .json(&payload)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to call Megalith API: {e}"))?;
-
-    if !response.status().is_success() {
-        let error_text = response
-            .text()
-            .await
-            .unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("Megalith API error: {error_text}"));
-    }
-
-    // Parse response to get the BOLT11 invoice
-    let api_response: serde_json::Value = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse Megalith response: {e}"))?;
-
-    let invoice = api_response
-        .get("payment")
-        .and_then(|v| v.get("bolt11"))
-        .and_then(|v| v.get("invoice"))
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| "Missing invoice in Megalith response".to_string())?;
-
-    Ok(invoice.to_string())
+    let provider =
+        crate::lsp::lsps1_provider(&state.args, Some(&form.provider)).map_err(|e| e.to_string())?;
+
+    crate::lsp::request_channel(
+        state,
+        &provider,
+        form.lsp_balance_sat,
+        0,
+        13140,
+        form.public,
+    )
+    .await
+    .map_err(|e| e.to_string())
 }
 
 async fn try_connect_peer(state: &AppState, form: &ConnectPeerForm) -> Result<(), String> {
@@ -600,6 +933,33 @@ async fn try_disconnect_peer(state: &AppState, form: &DisconnectPeerForm) -> Res
     Ok(())
 }
 
+async fn try_update_channel_config(
+    state: &AppState,
+    form: &ChannelConfigForm,
+) -> Result<ChannelConfigInfo, String> {
+    // An empty field keeps the current dust exposure limit
+    let max_dust_htlc_exposure_msat = match form.max_dust_htlc_exposure_msat.trim() {
+        "" => None,
+        limit => Some(
+            limit
+                .parse::<u64>()
+                .map_err(|_| "Invalid dust exposure limit".to_string())?,
+        ),
+    };
+
+    let request = UpdateChannelConfigRequest {
+        user_channel_id: form.user_channel_id.clone(),
+        counterparty_node_id: parse_node_id(&form.counterparty_node_id)?,
+        forwarding_fee_base_msat: Some(form.forwarding_fee_base_msat),
+        forwarding_fee_proportional_millionths: Some(form.forwarding_fee_proportional_millionths),
+        cltv_expiry_delta: Some(form.cltv_expiry_delta),
+        max_dust_htlc_exposure_msat,
+        max_dust_htlc_exposure_multiplier: None,
+    };
+
+    crate::forwarding::update_channel_config(state, &request).map_err(|e| e.to_string())
+}
+
 async fn try_close_channel(state: &AppState, form: &CloseChannelForm) -> Result<(), String> {
     let node_id = parse_node_id(&form.counterparty_node_id).map_err(|e| e.to_string())?;
 
@@ -644,7 +1004,102 @@ pub async fn request_channel_submit(
             );
             Html(html.into_string())
         }
-        Err(error) => Html(request_channel_form(Some(&error)).into_string()),
+        Err(error) => Html(
+            request_channel_form(&crate::lsp::lsps1_providers(&state.args), Some(&error))
+                .into_string(),
+        ),
+    }
+}
+
+pub async fn order_channel_submit(
+    State(state): State<AppState>,
+    Form(form): Form<OrderChannelForm>,
+) -> Html<String> {
+    let request = CreateChannelOrderRequest {
+        lsp_balance_sat: form.lsp_balance_sat,
+        client_balance_sat: 0,
+        channel_expiry_blocks: 13140,
+        public: form.public,
+    };
+
+    match crate::lsp::create_order(&state, &request).await {
+        Ok(order) => Html(
+            success_replacement(
+                "Channel Quote",
+                "Pay the order to have the LSP open the channel:",
+                channel_order_quote(&order),
+            )
+            .into_string(),
+        ),
+        Err(error) => Html(order_channel_form(Some(&error.to_string())).into_string()),
+    }
+}
+
+pub async fn pay_channel_order_submit(
+    State(state): State<AppState>,
+    Form(form): Form<PayChannelOrderForm>,
+) -> Html<String> {
+    match crate::lsp::pay_order(&state, &form.order_id, form.method).await {
+        Ok(_) => Html(
+            success_message("Order paid! The LSP opens the channel once the payment is received.")
+                .into_string(),
+        ),
+        Err(error) => {
+            let order =
+                crate::db::get_channel_order(&mut *state.db.get_connection().await, &form.order_id)
+                    .await
+                    .map(crate::db::channel_order_info);
+
+            match order {
+                Some(order) => {
+                    Html(pay_channel_order_form(&order, Some(&error.to_string())).into_string())
+                }
+                None => Html(success_message(&error.to_string()).into_string()),
+            }
+        }
+    }
+}
+
+pub async fn refresh_channel_order_submit(
+    State(state): State<AppState>,
+    Form(form): Form<ChannelOrderForm>,
+) -> Html<String> {
+    match crate::lsp::refresh_order(&state, &form.order_id).await {
+        Ok(order) => match order.funding_outpoint {
+            Some(funding_outpoint) => {
+                Html(success_message(&format!("Channel opened: {funding_outpoint}")).into_string())
+            }
+            None => Html(refresh_channel_order_form(&form.order_id, None).into_string()),
+        },
+        Err(error) => {
+            Html(refresh_channel_order_form(&form.order_id, Some(&error.to_string())).into_string())
+        }
+    }
+}
+
+pub async fn channel_config_submit(
+    State(state): State<AppState>,
+    Form(form): Form<ChannelConfigForm>,
+) -> Html<String> {
+    match try_update_channel_config(&state, &form).await {
+        Ok(_) => Html(success_message("Fee policy updated!").into_string()),
+        Err(error) => Html(
+            channel_config_form(
+                &form.user_channel_id,
+                &form.counterparty_node_id,
+                &ChannelConfigInfo {
+                    forwarding_fee_base_msat: form.forwarding_fee_base_msat,
+                    forwarding_fee_proportional_millionths: form
+                        .forwarding_fee_proportional_millionths,
+                    cltv_expiry_delta: form.cltv_expiry_delta,
+                    max_dust_htlc_exposure_msat: form.max_dust_htlc_exposure_msat.parse().ok(),
+                    max_dust_htlc_exposure_multiplier: None,
+                },
+                0,
+                Some(&error),
+            )
+            .into_string(),
+        ),
     }
 }
 
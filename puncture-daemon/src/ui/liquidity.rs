@@ -0,0 +1,163 @@
+use axum::{Extension, extract::State, response::Html};
+use maud::{Markup, html};
+
+use puncture_daemon_db::models::LiquidityDecisionRecord;
+
+use super::auth::UiSession;
+use super::shared::{base_template, format_sats, format_timestamp, success_message};
+use crate::AppState;
+use crate::liquidity::LiquidityMode;
+
+pub async fn liquidity_page(
+    State(state): State<AppState>,
+    Extension(session): Extension<UiSession>,
+) -> Html<String> {
+    let decisions =
+        crate::db::list_liquidity_decisions(&mut *state.db.get_connection().await, 100).await;
+
+    let html = liquidity_template(&state, &decisions, &session.csrf_token);
+
+    Html(html.into_string())
+}
+
+fn liquidity_template(
+    state: &AppState,
+    decisions: &[LiquidityDecisionRecord],
+    csrf_token: &str,
+) -> Markup {
+    let content = html! {
+        div class="card" {
+            div class="card-body" {
+                h5 class="card-title" { "Liquidity Decisions" }
+                @if decisions.is_empty() {
+                    div class="p-4 text-center text-muted" {
+                        "No liquidity decisions yet."
+                    }
+                } @else {
+                    div class="table-responsive" {
+                        table class="table table-sm align-middle" {
+                            thead {
+                                tr {
+                                    th { "Time" }
+                                    th { "Action" }
+                                    th { "Inbound" }
+                                    th { "Outbound" }
+                                    th { "Liabilities" }
+                                    th { "On-chain" }
+                                    th { "Reason" }
+                                    th { "Outcome" }
+                                }
+                            }
+                            tbody {
+                                @for decision in decisions {
+                                    tr {
+                                        td class="text-muted font-monospace small text-nowrap" {
+                                            (format_timestamp(decision.created_at))
+                                        }
+                                        td class="text-nowrap" {
+                                            @match decision.action.as_str() {
+                                                "alert" => span class="badge bg-danger" { "Alert" },
+                                                "buy_inbound" => span class="badge bg-primary" { "Buy Inbound" },
+                                                _ => span class="badge bg-primary" { "Open Outbound" },
+                                            }
+                                            @if decision.dry_run {
+                                                " "
+                                                span class="badge bg-secondary" { "Dry Run" }
+                                            }
+                                        }
+                                        td class="text-nowrap" { (format_sats(decision.inbound_capacity_msat as u64 / 1000)) " ₿" }
+                                        td class="text-nowrap" { (format_sats(decision.outbound_capacity_msat as u64 / 1000)) " ₿" }
+                                        td class="text-nowrap" { (format_sats(decision.liabilities_msat as u64 / 1000)) " ₿" }
+                                        td class="text-nowrap" { (format_sats(decision.onchain_balance_sats as u64)) " ₿" }
+                                        td class="small" { (decision.reason) }
+                                        td class="small text-break" {
+                                            @if let Some(outcome) = &decision.outcome {
+                                                (outcome)
+                                            } @else {
+                                                span class="text-muted" { "-" }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    };
+
+    let args = &state.args;
+
+    let action_sidebar = html! {
+        table class="table table-sm table-borderless mb-3" {
+            tbody {
+                tr {
+                    td class="fw-bold" { "Mode" }
+                    td {
+                        @match args.liquidity_mode {
+                            LiquidityMode::Off => "Off",
+                            LiquidityMode::DryRun => "Dry Run",
+                            LiquidityMode::Auto => "Auto",
+                        }
+                    }
+                }
+                tr {
+                    td class="fw-bold" { "Min Inbound" }
+                    td { (format_sats(args.liquidity_min_inbound_sats)) " ₿" }
+                }
+                tr {
+                    td class="fw-bold" { "Min Outbound" }
+                    td { (args.liquidity_min_outbound_percent) "% of liabilities" }
+                }
+                tr {
+                    td class="fw-bold" { "Channel Size" }
+                    td { (format_sats(args.liquidity_channel_sats)) " ₿" }
+                }
+            }
+        }
+
+        (check_liquidity_form(None))
+    };
+
+    base_template(
+        "Liquidity",
+        "/liquidity",
+        csrf_token,
+        content,
+        action_sidebar,
+    )
+}
+
+fn check_liquidity_form(error: Option<&str>) -> Markup {
+    html! {
+        form hx-post="/liquidity/check"
+             hx-target="this"
+             hx-swap="outerHTML" {
+
+            @if let Some(err) = error {
+                div class="alert alert-danger" { (err) }
+            }
+
+            button type="submit" class="btn btn-outline-primary w-100" { "Check Now" }
+            div class="form-text" { "Unless the mode is auto the decision is only logged" }
+        }
+    }
+}
+
+pub async fn check_liquidity_submit(State(state): State<AppState>) -> Html<String> {
+    match crate::liquidity::check(&state).await {
+        Ok(Some(decision)) => Html(
+            success_message(&format!(
+                "{} ({}). Reload the page to see the decision.",
+                decision.reason,
+                decision.outcome.as_deref().unwrap_or("not executed")
+            ))
+            .into_string(),
+        ),
+        Ok(None) => {
+            Html(success_message("No action required or channels are still pending.").into_string())
+        }
+        Err(error) => Html(check_liquidity_form(Some(&error.to_string())).into_string()),
+    }
+}
@@ -1,11 +1,14 @@
+mod audit;
+pub mod auth;
 mod db;
 mod lightning;
+mod liquidity;
 mod onchain;
 mod shared;
 mod users;
 
 use axum::{
-    Router,
+    Router, middleware,
     routing::{get, post},
 };
 use tokio::net::TcpListener;
@@ -18,7 +21,15 @@ pub async fn run_ui(app_state: AppState, ct: CancellationToken) {
         .await
         .expect("Failed to bind UI server");
 
-    axum::serve(listener, create_router().with_state(app_state))
+    let router = create_router()
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            auth::require_session,
+        ))
+        .route("/login", get(auth::login_page).post(auth::login_submit))
+        .with_state(app_state);
+
+    axum::serve(listener, router)
         .with_graceful_shutdown(ct.cancelled_owned())
         .await
         .expect("Failed to start UI server");
@@ -36,6 +47,22 @@ fn create_router() -> Router<AppState> {
             "/lightning/channel/request",
             post(lightning::request_channel_submit),
         )
+        .route(
+            "/lightning/order/create",
+            post(lightning::order_channel_submit),
+        )
+        .route(
+            "/lightning/order/pay",
+            post(lightning::pay_channel_order_submit),
+        )
+        .route(
+            "/lightning/order/refresh",
+            post(lightning::refresh_channel_order_submit),
+        )
+        .route(
+            "/lightning/channel/config",
+            post(lightning::channel_config_submit),
+        )
         .route(
             "/lightning/channel/close",
             post(lightning::close_channel_submit),
@@ -54,5 +81,12 @@ fn create_router() -> Router<AppState> {
         .route("/onchain/drain", post(onchain::onchain_drain_submit))
         .route("/users", get(users::users_page))
         .route("/users/invite", post(users::invite_submit))
+        .route("/users/invite/revoke", post(users::revoke_invite_submit))
         .route("/users/recover", post(users::recovery_submit))
+        .route("/users/status", post(users::user_status_submit))
+        .route("/users/adjust", post(users::adjust_balance_submit))
+        .route("/audit", get(audit::audit_page))
+        .route("/liquidity", get(liquidity::liquidity_page))
+        .route("/liquidity/check", post(liquidity::check_liquidity_submit))
+        .route("/logout", post(auth::logout_submit))
 }
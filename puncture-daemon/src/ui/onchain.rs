@@ -1,17 +1,22 @@
-use axum::{Form, extract::State, response::Html};
+use axum::{Extension, Form, extract::State, response::Html};
 use bitcoin::Txid;
 use bitcoin::{Address, address::NetworkUnchecked};
 use ldk_node::payment::{ConfirmationStatus, PaymentKind};
 use maud::{Markup, html};
 use serde::Deserialize;
 
+use super::auth::UiSession;
 use super::shared::{
     base_template, copyable_hex_input, format_sats, format_timestamp, qr_code_with_copy,
     success_replacement,
 };
 use crate::AppState;
 
-pub fn onchain_template(onchain_balance: u64, payments: Vec<(Txid, ConfirmationStatus)>) -> Markup {
+pub fn onchain_template(
+    onchain_balance: u64,
+    payments: Vec<(Txid, ConfirmationStatus)>,
+    csrf_token: &str,
+) -> Markup {
     let content = html! {
         div class="row g-4" {
             div class="col-12" {
@@ -143,7 +148,7 @@ pub fn onchain_template(onchain_balance: u64, payments: Vec<(Txid, ConfirmationS
         }
     };
 
-    base_template("Onchain", "/onchain", content, action_sidebar)
+    base_template("Onchain", "/onchain", csrf_token, content, action_sidebar)
 }
 
 fn receive_bitcoin_form() -> Markup {
@@ -213,7 +218,10 @@ pub struct OnchainDrainForm {
     pub address: String,
 }
 
-pub async fn onchain_page(State(state): State<AppState>) -> Html<String> {
+pub async fn onchain_page(
+    State(state): State<AppState>,
+    Extension(session): Extension<UiSession>,
+) -> Html<String> {
     let balance = state.node.list_balances().total_onchain_balance_sats;
 
     let mut payments = state
@@ -236,7 +244,7 @@ pub async fn onchain_page(State(state): State<AppState>) -> Html<String> {
 
     payments.reverse();
 
-    let html = onchain_template(balance, payments);
+    let html = onchain_template(balance, payments, &session.csrf_token);
 
     Html(html.into_string())
 }
@@ -61,6 +61,7 @@ pub fn copyable_hex_input(value: &str, size: Option<&str>) -> Markup {
 pub fn base_template(
     title: &str,
     current_path: &str,
+    csrf_token: &str,
     content: Markup,
     action_sidebar: Markup,
 ) -> Markup {
@@ -76,7 +77,8 @@ pub fn base_template(
                     ".action-sidebar { background-color: #f8f9fa !important; min-height: 100vh; }"
                 }
             }
-            body {
+            // htmx sends the session's CSRF token with every form submission
+            body hx-headers=(format!(r#"{{"X-CSRF-Token": "{csrf_token}"}}"#)) {
                 div id="toast-container" class="position-fixed top-0 end-0 p-3" style="z-index: 1100;" {}
 
                 nav class="navbar navbar-expand-lg navbar-dark bg-dark" {
@@ -98,8 +100,19 @@ pub fn base_template(
                                     "nav-link" @if current_path == "/users" { " active" }
                                 } href="/users" { "Users" }
                             }
+                            li class="nav-item" {
+                                a class={
+                                    "nav-link" @if current_path == "/audit" { " active" }
+                                } href="/audit" { "Audit" }
+                            }
+                            li class="nav-item" {
+                                a class={
+                                    "nav-link" @if current_path == "/liquidity" { " active" }
+                                } href="/liquidity" { "Liquidity" }
+                            }
                         }
 
+                        button type="button" class="btn btn-outline-light btn-sm" hx-post="/logout" { "Logout" }
                     }
                 }
                 div class="container-fluid" {
@@ -1,16 +1,22 @@
-use axum::{Form, extract::State, response::Html};
+use axum::{Extension, Form, extract::State, response::Html};
 use maud::{Markup, html};
-use puncture_core::PunctureCode;
+use puncture_cli_core::{InviteInfo, UserStatus};
+use puncture_core::{PunctureCode, unix_time};
 use rand::Rng;
 use serde::Deserialize;
 
+use super::auth::UiSession;
 use super::shared::{
     base_template, copyable_hex_input, format_sats, format_timestamp, qr_code_with_copy,
-    success_replacement,
+    success_message, success_replacement,
 };
 use crate::AppState;
+use crate::convert::IntoPayment;
 
-pub async fn users_page(State(state): State<AppState>) -> Html<String> {
+pub async fn users_page(
+    State(state): State<AppState>,
+    Extension(session): Extension<UiSession>,
+) -> Html<String> {
     let users = super::db::list_users(&mut *state.db.get_connection().await).await;
 
     // Filter to only users with recovery names and sort by recovery_name
@@ -21,12 +27,18 @@ pub async fn users_page(State(state): State<AppState>) -> Html<String> {
 
     filtered_users.sort_by_key(|a| a.recovery_name.as_ref().unwrap().to_string());
 
-    let html = users_template(&filtered_users);
+    let invites = crate::db::list_invites(&mut *state.db.get_connection().await).await;
+
+    let html = users_template(&filtered_users, &invites, &session.csrf_token);
 
     Html(html.into_string())
 }
 
-fn users_template(users: &[puncture_cli_core::UserInfo]) -> Markup {
+fn users_template(
+    users: &[puncture_cli_core::UserInfo],
+    invites: &[InviteInfo],
+    csrf_token: &str,
+) -> Markup {
     let content = html! {
         // Users Accordion
         @if users.is_empty() {
@@ -62,6 +74,15 @@ fn users_template(users: &[puncture_cli_core::UserInfo]) -> Markup {
                                             td class="fw-bold" { "Created" }
                                             td class="text-muted font-monospace" { (format_timestamp(user.created_at)) }
                                         }
+                                        tr {
+                                            td class="fw-bold" { "Status" }
+                                            td {
+                                                (status_badge(user.status))
+                                                @if let Some(reason) = &user.status_reason {
+                                                    span class="text-muted ms-2" { (reason) }
+                                                }
+                                            }
+                                        }
                                     }
                                 }
                                 div class="d-flex justify-content-end" {
@@ -91,6 +112,46 @@ fn users_template(users: &[puncture_cli_core::UserInfo]) -> Markup {
                 }
             }
         }
+
+        @if !invites.is_empty() {
+            div class="card mt-4" {
+                div class="card-body" {
+                    h5 class="card-title" { "Invites" }
+                    div class="table-responsive" {
+                        table class="table table-sm align-middle mb-0" {
+                            thead {
+                                tr {
+                                    th { "ID" }
+                                    th { "Users" }
+                                    th { "Created" }
+                                    th { "Expires" }
+                                    th { "Status" }
+                                }
+                            }
+                            tbody {
+                                @for invite in invites {
+                                    tr {
+                                        td class="font-monospace small" { (invite.id[..16]) "..." }
+                                        td { (invite.user_count) " / " (invite.user_limit) }
+                                        td class="text-muted font-monospace" { (format_timestamp(invite.created_at)) }
+                                        td class="text-muted font-monospace" { (format_timestamp(invite.expires_at)) }
+                                        td {
+                                            @if invite.revoked_at.is_some() {
+                                                span class="badge bg-secondary" { "Revoked" }
+                                            } @else if invite.expires_at < unix_time() {
+                                                span class="badge bg-warning" { "Expired" }
+                                            } @else {
+                                                (revoke_invite_form(&invite.id))
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
     };
 
     let action_sidebar = html! {
@@ -108,10 +169,38 @@ fn users_template(users: &[puncture_cli_core::UserInfo]) -> Markup {
                     }
                 }
             }
+
+            // Freeze, suspend, delete or reactivate users
+            div class="accordion-item" {
+                h2 class="accordion-header" {
+                    button class="accordion-button collapsed" type="button" data-bs-toggle="collapse" data-bs-target="#userStatusCollapse" aria-expanded="false" aria-controls="userStatusCollapse" {
+                        "User Status"
+                    }
+                }
+                div id="userStatusCollapse" class="accordion-collapse collapse" data-bs-parent="#usersActionsAccordion" {
+                    div class="accordion-body" {
+                        (user_status_form(None))
+                    }
+                }
+            }
+
+            // Credit or debit a user's balance
+            div class="accordion-item" {
+                h2 class="accordion-header" {
+                    button class="accordion-button collapsed" type="button" data-bs-toggle="collapse" data-bs-target="#adjustBalanceCollapse" aria-expanded="false" aria-controls="adjustBalanceCollapse" {
+                        "Adjust Balance"
+                    }
+                }
+                div id="adjustBalanceCollapse" class="accordion-collapse collapse" data-bs-parent="#usersActionsAccordion" {
+                    div class="accordion-body" {
+                        (adjust_balance_form(None))
+                    }
+                }
+            }
         }
     };
 
-    base_template("Users", "/users", content, action_sidebar)
+    base_template("Users", "/users", csrf_token, content, action_sidebar)
 }
 
 // Form structs
@@ -121,6 +210,25 @@ pub struct InviteForm {
     pub user_limit: u32,
 }
 
+#[derive(Deserialize)]
+pub struct UserStatusForm {
+    pub user_pk: String,
+    pub status: String,
+    pub reason: String,
+}
+
+#[derive(Deserialize)]
+pub struct AdjustBalanceForm {
+    pub user_pk: String,
+    pub amount_msat: i64,
+    pub memo: String,
+}
+
+#[derive(Deserialize)]
+pub struct RevokeInviteForm {
+    pub invite_id: String,
+}
+
 #[derive(Deserialize)]
 pub struct RecoveryForm {
     pub user_pk: String,
@@ -146,6 +254,137 @@ fn invite_form() -> Markup {
     }
 }
 
+fn status_badge(status: UserStatus) -> Markup {
+    let class = match status {
+        UserStatus::Active => "badge bg-success",
+        UserStatus::Frozen => "badge bg-info",
+        UserStatus::Suspended => "badge bg-warning",
+        UserStatus::Deleted => "badge bg-danger",
+    };
+
+    html! {
+        span class=(class) { (status) }
+    }
+}
+
+fn user_status_form(error: Option<&str>) -> Markup {
+    html! {
+        form hx-post="/users/status"
+             hx-target="this"
+             hx-swap="outerHTML" {
+
+            @if let Some(err) = error {
+                div class="alert alert-danger" { (err) }
+            }
+
+            div class="mb-3" {
+                label for="status-user-pk" class="form-label" { "Public Key" }
+                input type="text" class="form-control font-monospace" id="status-user-pk" name="user_pk" required {}
+            }
+            div class="mb-3" {
+                label for="status-status" class="form-label" { "Status" }
+                select class="form-select" id="status-status" name="status" {
+                    option value="active" { "Active" }
+                    option value="frozen" { "Frozen - can receive but not send" }
+                    option value="suspended" { "Suspended" }
+                    option value="deleted" { "Deleted - permanent" }
+                }
+            }
+            div class="mb-3" {
+                label for="status-reason" class="form-label" { "Reason" }
+                input type="text" class="form-control" id="status-reason" name="reason" {}
+                div class="form-text" { "Shown to the user when a request is rejected" }
+            }
+            button type="submit" class="btn btn-outline-primary w-100" { "Set Status" }
+        }
+    }
+}
+
+async fn try_set_user_status(state: &AppState, form: UserStatusForm) -> Result<(), String> {
+    let status = form.status.parse::<UserStatus>()?;
+
+    let mut conn = state.db.get_connection().await;
+
+    let user = crate::db::get_user_info(&mut conn, form.user_pk.clone())
+        .await
+        .ok_or("Unknown public key".to_string())?;
+
+    if user.status == UserStatus::Deleted {
+        return Err("Deleted users cannot be changed".to_string());
+    }
+
+    let reason = Some(form.reason).filter(|reason| !reason.is_empty());
+
+    crate::db::set_user_status(&mut conn, form.user_pk.clone(), status, reason).await;
+
+    state.event_bus.send_status_event(form.user_pk);
+
+    Ok(())
+}
+
+fn adjust_balance_form(error: Option<&str>) -> Markup {
+    html! {
+        form hx-post="/users/adjust"
+             hx-target="this"
+             hx-swap="outerHTML" {
+
+            @if let Some(err) = error {
+                div class="alert alert-danger" { (err) }
+            }
+
+            div class="mb-3" {
+                label for="adjust-user-pk" class="form-label" { "Public Key" }
+                input type="text" class="form-control font-monospace" id="adjust-user-pk" name="user_pk" required {}
+            }
+            div class="mb-3" {
+                label for="adjust-amount" class="form-label" { "Amount (msat)" }
+                input type="number" class="form-control" id="adjust-amount" name="amount_msat" required {}
+                div class="form-text" { "Use a negative amount to debit the user" }
+            }
+            div class="mb-3" {
+                label for="adjust-memo" class="form-label" { "Memo" }
+                input type="text" class="form-control" id="adjust-memo" name="memo" required {}
+                div class="form-text" { "Shown in the user's payment history" }
+            }
+            button type="submit" class="btn btn-outline-primary w-100" { "Adjust Balance" }
+        }
+    }
+}
+
+async fn try_adjust_balance(state: &AppState, form: AdjustBalanceForm) -> Result<(), String> {
+    let (record, balance_msat) = crate::db::adjust_balance(
+        &mut *state.db.get_connection().await,
+        form.user_pk.clone(),
+        form.amount_msat,
+        form.memo,
+    )
+    .await?;
+
+    state
+        .event_bus
+        .send_balance_event(form.user_pk.clone(), balance_msat);
+
+    state
+        .event_bus
+        .send_payment_event(form.user_pk, record.into_payment(true));
+
+    Ok(())
+}
+
+fn revoke_invite_form(invite_id: &str) -> Markup {
+    html! {
+        form hx-post="/users/invite/revoke"
+             hx-target="this"
+             hx-swap="outerHTML"
+             hx-confirm="Revoke this invite? No more users will be able to register with it." {
+
+            input type="hidden" name="invite_id" value=(invite_id) {}
+
+            button type="submit" class="btn btn-outline-danger btn-sm" { "Revoke" }
+        }
+    }
+}
+
 fn recovery_form_for_user(user_pk: &str, _user_index: usize) -> Markup {
     html! {
         form hx-post="/users/recover"
@@ -187,6 +426,44 @@ pub async fn invite_submit(
     Html(html.into_string())
 }
 
+pub async fn revoke_invite_submit(
+    State(state): State<AppState>,
+    Form(form): Form<RevokeInviteForm>,
+) -> Html<String> {
+    let html =
+        if crate::db::revoke_invite(&mut *state.db.get_connection().await, &form.invite_id).await {
+            html! {
+                span class="badge bg-secondary" { "Revoked" }
+            }
+        } else {
+            html! {
+                span class="badge bg-danger" { "Unknown invite" }
+            }
+        };
+
+    Html(html.into_string())
+}
+
+pub async fn user_status_submit(
+    State(state): State<AppState>,
+    Form(form): Form<UserStatusForm>,
+) -> Html<String> {
+    match try_set_user_status(&state, form).await {
+        Ok(()) => Html(success_message("User status updated").into_string()),
+        Err(error) => Html(user_status_form(Some(&error)).into_string()),
+    }
+}
+
+pub async fn adjust_balance_submit(
+    State(state): State<AppState>,
+    Form(form): Form<AdjustBalanceForm>,
+) -> Html<String> {
+    match try_adjust_balance(&state, form).await {
+        Ok(()) => Html(success_message("Balance adjusted").into_string()),
+        Err(error) => Html(adjust_balance_form(Some(&error)).into_string()),
+    }
+}
+
 pub async fn recovery_submit(
     State(state): State<AppState>,
     Form(form): Form<RecoveryForm>,
@@ -6,8 +6,11 @@ use bitcoin::{Address, Network};
 use serde::de::DeserializeOwned;
 
 use puncture_cli_core::{
-    BalancesResponse, ChannelInfo, InviteResponse, ListChannelsResponse, ListUsersResponse,
-    OnchainReceiveResponse, OpenChannelResponse, RecoverResponse, UserInfo,
+    AdjustBalanceResponse, AuditEntry, AuditListResponse, BackupInfo, BalancesResponse,
+    ChannelConfigInfo, ChannelInfo, ForwardingReportResponse, ImportResponse, InviteInfo,
+    InviteResponse, ListChannelsResponse, ListUsersResponse, OnchainReceiveResponse,
+    OpenChannelResponse, RecoverResponse, RequestChannelResponse, Settings, SignedArchive,
+    SolvencyResponse, StatusResponse, UserInfo, UserStatus,
 };
 
 trait RunPunctureCli {
@@ -31,8 +34,16 @@ impl RunPunctureCli for Command {
     }
 }
 
+fn puncture_cli() -> Command {
+    let mut command = Command::new("target/debug/puncture-cli");
+
+    command.env("PUNCTURE_DATA_DIR", "./data-dir-testing/daemon/puncture");
+
+    command
+}
+
 pub fn onchain_receive() -> Result<Address> {
-    Command::new("target/debug/puncture-cli")
+    puncture_cli()
         .arg("ldk")
         .arg("onchain")
         .arg("receive")
@@ -46,14 +57,64 @@ pub fn onchain_receive() -> Result<Address> {
 }
 
 pub fn balances() -> Result<BalancesResponse> {
-    Command::new("target/debug/puncture-cli")
+    puncture_cli()
         .arg("ldk")
         .arg("balances")
         .run_puncture_cli::<BalancesResponse>()
 }
 
+pub fn status() -> Result<StatusResponse> {
+    puncture_cli()
+        .arg("status")
+        .run_puncture_cli::<StatusResponse>()
+}
+
+pub fn config_reload() -> Result<Settings> {
+    puncture_cli()
+        .arg("config")
+        .arg("reload")
+        .run_puncture_cli::<Settings>()
+}
+
+pub fn solvency() -> Result<SolvencyResponse> {
+    puncture_cli()
+        .arg("ldk")
+        .arg("solvency")
+        .run_puncture_cli::<SolvencyResponse>()
+}
+
+pub fn solvency_publish() -> Result<SolvencyResponse> {
+    puncture_cli()
+        .arg("ldk")
+        .arg("solvency")
+        .arg("--publish")
+        .run_puncture_cli::<SolvencyResponse>()
+}
+
+pub fn admin_export() -> Result<SignedArchive> {
+    puncture_cli()
+        .arg("admin")
+        .arg("export")
+        .run_puncture_cli::<SignedArchive>()
+}
+
+pub fn admin_import(path: &str) -> Result<ImportResponse> {
+    puncture_cli()
+        .arg("admin")
+        .arg("import")
+        .arg(path)
+        .run_puncture_cli::<ImportResponse>()
+}
+
+pub fn backup_create() -> Result<BackupInfo> {
+    puncture_cli()
+        .arg("backup")
+        .arg("create")
+        .run_puncture_cli::<BackupInfo>()
+}
+
 pub fn open_channel(node_id_b: PublicKey, ldk_port_b: u16) -> Result<String> {
-    Command::new("target/debug/puncture-cli")
+    puncture_cli()
         .arg("ldk")
         .arg("channel")
         .arg("open")
@@ -66,8 +127,19 @@ pub fn open_channel(node_id_b: PublicKey, ldk_port_b: u16) -> Result<String> {
         .map(|response| response.channel_id)
 }
 
+pub fn request_channel(lsp_balance_sat: u64) -> Result<RequestChannelResponse> {
+    puncture_cli()
+        .arg("ldk")
+        .arg("channel")
+        .arg("request")
+        .arg(lsp_balance_sat.to_string())
+        .arg("--provider")
+        .arg("mock")
+        .run_puncture_cli::<RequestChannelResponse>()
+}
+
 pub fn list_channels() -> Result<Vec<ChannelInfo>> {
-    Command::new("target/debug/puncture-cli")
+    puncture_cli()
         .arg("ldk")
         .arg("channel")
         .arg("list")
@@ -75,25 +147,90 @@ pub fn list_channels() -> Result<Vec<ChannelInfo>> {
         .map(|response| response.channels)
 }
 
+pub fn channel_config(
+    channel: &ChannelInfo,
+    forwarding_fee_base_msat: u32,
+    forwarding_fee_proportional_millionths: u32,
+) -> Result<ChannelConfigInfo> {
+    puncture_cli()
+        .arg("ldk")
+        .arg("channel")
+        .arg("config")
+        .arg(&channel.user_channel_id)
+        .arg(channel.counterparty_node_id.to_string())
+        .arg("--forwarding-fee-base-msat")
+        .arg(forwarding_fee_base_msat.to_string())
+        .arg("--forwarding-fee-proportional-millionths")
+        .arg(forwarding_fee_proportional_millionths.to_string())
+        .run_puncture_cli::<ChannelConfigInfo>()
+}
+
+pub fn forwarding_report() -> Result<ForwardingReportResponse> {
+    puncture_cli()
+        .arg("ldk")
+        .arg("forwarding")
+        .run_puncture_cli::<ForwardingReportResponse>()
+}
+
 pub fn invite() -> Result<InviteResponse> {
-    Command::new("target/debug/puncture-cli")
+    puncture_cli()
         .arg("user")
         .arg("invite")
         .run_puncture_cli::<InviteResponse>()
 }
 
+pub fn invite_revoke(invite_id: String) -> Result<InviteInfo> {
+    puncture_cli()
+        .arg("user")
+        .arg("invite")
+        .arg("revoke")
+        .arg(invite_id)
+        .run_puncture_cli::<InviteInfo>()
+}
+
 pub fn recover(user_pk: String) -> Result<RecoverResponse> {
-    Command::new("target/debug/puncture-cli")
+    puncture_cli()
         .arg("user")
         .arg("recover")
         .arg(user_pk)
         .run_puncture_cli::<RecoverResponse>()
 }
 
+pub fn user_status(user_pk: String, status: UserStatus) -> Result<UserInfo> {
+    puncture_cli()
+        .arg("user")
+        .arg("status")
+        .arg(user_pk)
+        .arg(status.as_str())
+        .run_puncture_cli::<UserInfo>()
+}
+
+pub fn user_adjust(user_pk: String, amount_msat: i64, memo: &str) -> Result<AdjustBalanceResponse> {
+    puncture_cli()
+        .arg("user")
+        .arg("adjust")
+        .arg(user_pk)
+        .arg("--amount-msat")
+        .arg(amount_msat.to_string())
+        .arg("--memo")
+        .arg(memo)
+        .run_puncture_cli::<AdjustBalanceResponse>()
+}
+
 pub fn list_users() -> Result<Vec<UserInfo>> {
-    Command::new("target/debug/puncture-cli")
+    puncture_cli()
         .arg("user")
         .arg("list")
         .run_puncture_cli::<ListUsersResponse>()
         .map(|response| response.users)
 }
+
+pub fn audit_list(action: &str) -> Result<Vec<AuditEntry>> {
+    puncture_cli()
+        .arg("audit")
+        .arg("list")
+        .arg("--action")
+        .arg(action)
+        .run_puncture_cli::<AuditListResponse>()
+        .map(|response| response.entries)
+}